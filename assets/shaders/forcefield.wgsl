@@ -3,18 +3,17 @@
 
 @group(1) @binding(0) var<uniform> color: vec4<f32>;
 @group(1) @binding(1) var<uniform> time: f32;
+// 1.0 when the field is up, goes down to 0.0 while it is being disabled
+@group(1) @binding(2) var<uniform> strength: f32;
 
 @fragment
 fn fragment(
     mesh: VertexOutput,
 ) -> @location(0) vec4<f32> {
     var border_width = 0.2;
-    var c1 = vec4<f32>(0.0, 0.1, 0.5, 0.0);
-    var c2 = vec4<f32>(0.0, 0.6, .7, 1.0);
-    //var c2 = vec4<f32>(1.0);
-    var n = simplexNoise3(vec3<f32>(mesh.world_position.xy * 1.0, time * 0.5));
-    //n = (n / 2.0) + 0.5;
-    //n = min(n, 1.0 - n);
+    var c1 = vec4<f32>(color.rgb * 0.2, 0.0);
+    var c2 = color;
+    var n = simplexNoise3(vec3<f32>(mesh.world_position.xy * 0.1, time * 0.5));
     n = smoothstep(-0.4, -0.0, n) * smoothstep(-0.4, -0.0, -n);
 
     var border_up = smoothstep(border_width, 0.0, mesh.uv.y);
@@ -23,7 +22,20 @@ fn fragment(
 
     n = max(border, n);
     var c = mix(c1, c2, n);
-    // return color;
-    return vec4<f32>(c);
-//    return vec4<f32>(t, t, t, 1.0);
+
+    // Disable animation: the field flickers and dissolves into noise, leaving a bright edge
+    // where it is breaking apart
+    var dissolve = simplexNoise3(vec3<f32>(mesh.world_position.xy * 0.3, time * 2.0)) * 0.5 + 0.5;
+    var flicker = 0.8 + 0.2 * sin(time * 60.0);
+    var remaining = strength * 1.1 - dissolve;
+    if remaining < 0.0 {
+        discard;
+    }
+    var edge = smoothstep(0.1, 0.0, remaining) * (1.0 - strength);
+    c = mix(c, vec4<f32>(1.0), edge);
+    if strength < 1.0 {
+        c.a = c.a * flicker;
+    }
+
+    return c;
 }
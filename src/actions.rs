@@ -0,0 +1,361 @@
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+const PRESSED_THRESHOLD: f32 = 0.5;
+const REBIND_AXIS_THRESHOLD: f32 = 0.8;
+
+pub struct ActionsPlugin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ThrustUp,
+    ThrustDown,
+    ThrustLeft,
+    ThrustRight,
+    Brake,
+    SwitchMovementMode,
+    Interact,
+    Advance,
+    Reset,
+    ToggleDebug,
+    QuickSave,
+    QuickLoad,
+    Pause,
+    Inventory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    Axis(GamepadAxisType, AxisDirection),
+}
+
+impl Binding {
+    /// Both bindings are keys, gamepad buttons or gamepad axes.
+    fn same_kind(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+pub struct ActionMap {
+    pub deadzone: f32,
+    pub bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use AxisDirection::*;
+        use Binding::*;
+
+        Self {
+            deadzone: 0.2,
+            bindings: vec![
+                (
+                    Action::ThrustUp,
+                    vec![Key(KeyCode::W), Axis(GamepadAxisType::LeftStickY, Positive)],
+                ),
+                (
+                    Action::ThrustDown,
+                    vec![Key(KeyCode::S), Axis(GamepadAxisType::LeftStickY, Negative)],
+                ),
+                (
+                    Action::ThrustLeft,
+                    vec![Key(KeyCode::A), Axis(GamepadAxisType::LeftStickX, Negative)],
+                ),
+                (
+                    Action::ThrustRight,
+                    vec![Key(KeyCode::D), Axis(GamepadAxisType::LeftStickX, Positive)],
+                ),
+                (
+                    Action::Brake,
+                    vec![Key(KeyCode::ShiftLeft), Button(GamepadButtonType::South)],
+                ),
+                (
+                    Action::SwitchMovementMode,
+                    vec![Key(KeyCode::M), Button(GamepadButtonType::RightTrigger)],
+                ),
+                (
+                    Action::Interact,
+                    vec![Key(KeyCode::E), Button(GamepadButtonType::North)],
+                ),
+                (
+                    Action::Advance,
+                    vec![Key(KeyCode::Space), Button(GamepadButtonType::East)],
+                ),
+                (
+                    Action::Reset,
+                    vec![Key(KeyCode::R), Button(GamepadButtonType::Select)],
+                ),
+                (Action::ToggleDebug, vec![Key(KeyCode::F1)]),
+                (Action::QuickSave, vec![Key(KeyCode::F5)]),
+                (Action::QuickLoad, vec![Key(KeyCode::F9)]),
+                (
+                    Action::Pause,
+                    vec![Key(KeyCode::Escape), Button(GamepadButtonType::Start)],
+                ),
+                (
+                    Action::Inventory,
+                    vec![Key(KeyCode::I), Button(GamepadButtonType::West)],
+                ),
+            ],
+        }
+    }
+}
+
+impl ActionMap {
+    /// Replaces the bindings of `action` of the same kind as `binding`, keeping its bindings of the
+    /// other kinds untouched.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let index = match self
+            .bindings
+            .iter()
+            .position(|(bound_action, _)| *bound_action == action)
+        {
+            Some(index) => index,
+            None => {
+                self.bindings.push((action, vec![]));
+                self.bindings.len() - 1
+            }
+        };
+
+        let bindings = &mut self.bindings[index].1;
+        bindings.retain(|bound| !bound.same_kind(&binding));
+        bindings.push(binding);
+    }
+
+    /// Gives their default bindings to the actions added after the map was saved.
+    pub fn add_missing_defaults(&mut self) {
+        for (action, bindings) in ActionMap::default().bindings {
+            if !self
+                .bindings
+                .iter()
+                .any(|(bound_action, _)| *bound_action == action)
+            {
+                self.bindings.push((action, bindings));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    previous: HashMap<Action, f32>,
+}
+
+impl ActionState {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) > PRESSED_THRESHOLD
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action)
+            && self.previous.get(&action).copied().unwrap_or_default() <= PRESSED_THRESHOLD
+    }
+
+    /// Thrust direction with a length between 0 and 1, proportional to the stick tilt on gamepads.
+    pub fn thrust(&self) -> Vec2 {
+        Vec2::new(
+            self.value(Action::ThrustRight) - self.value(Action::ThrustLeft),
+            self.value(Action::ThrustUp) - self.value(Action::ThrustDown),
+        )
+        .clamp_length_max(1.0)
+    }
+}
+
+/// When set, the next key, button or axis pressed is bound to the action instead of triggering
+/// anything.
+#[derive(Debug, Default, Resource)]
+pub struct Rebinding(pub Option<Action>);
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        // Read from the settings when they are loaded first
+        app.init_resource::<ActionMap>()
+            .init_resource::<ActionState>()
+            .init_resource::<Rebinding>()
+            .add_systems(
+                PreUpdate,
+                (
+                    log_gamepad_connections,
+                    update_action_state,
+                    capture_rebinding,
+                )
+                    .chain()
+                    .after(InputSystem),
+            );
+    }
+}
+
+fn stick_partner(axis_type: GamepadAxisType) -> Option<GamepadAxisType> {
+    match axis_type {
+        GamepadAxisType::LeftStickX => Some(GamepadAxisType::LeftStickY),
+        GamepadAxisType::LeftStickY => Some(GamepadAxisType::LeftStickX),
+        GamepadAxisType::RightStickX => Some(GamepadAxisType::RightStickY),
+        GamepadAxisType::RightStickY => Some(GamepadAxisType::RightStickX),
+        _ => None,
+    }
+}
+
+/// The deadzone is applied on the whole stick rather than on each axis so diagonals are not
+/// snapped to the closest axis. The tilt is then rescaled to still cover the `[0, 1]` range.
+fn axis_value(
+    axes: &Axis<GamepadAxis>,
+    gamepad: Gamepad,
+    axis_type: GamepadAxisType,
+    deadzone: f32,
+) -> Option<f32> {
+    let value = axes.get(GamepadAxis::new(gamepad, axis_type))?;
+    let magnitude = match stick_partner(axis_type) {
+        Some(partner) => Vec2::new(
+            value,
+            axes.get(GamepadAxis::new(gamepad, partner))
+                .unwrap_or_default(),
+        )
+        .length(),
+        None => value.abs(),
+    };
+
+    if magnitude <= deadzone {
+        return Some(0.0);
+    }
+
+    let tilt = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    Some(value * tilt / magnitude)
+}
+
+// Gamepads are looked up every frame in `Gamepads` so controllers can be plugged at any time.
+fn log_gamepad_connections(mut events: EventReader<GamepadConnectionEvent>) {
+    for event in events.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                info!("Gamepad {} connected: {}", event.gamepad.id, info.name)
+            }
+            GamepadConnection::Disconnected => {
+                info!("Gamepad {} disconnected", event.gamepad.id)
+            }
+        }
+    }
+}
+
+fn digital(pressed: bool) -> f32 {
+    if pressed {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn update_action_state(
+    mut action_state: ResMut<ActionState>,
+    action_map: Res<ActionMap>,
+    rebinding: Res<Rebinding>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    let action_state = &mut *action_state;
+    std::mem::swap(&mut action_state.values, &mut action_state.previous);
+    action_state.values.clear();
+
+    for (action, bindings) in action_map.bindings.iter() {
+        let value = bindings
+            .iter()
+            .map(|binding| match *binding {
+                Binding::Key(key) => digital(keys.pressed(key)),
+                Binding::Button(button_type) => digital(
+                    gamepads
+                        .iter()
+                        .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type))),
+                ),
+                Binding::Axis(axis_type, direction) => gamepads
+                    .iter()
+                    .filter_map(|gamepad| {
+                        axis_value(&axes, gamepad, axis_type, action_map.deadzone)
+                    })
+                    .map(|value| match direction {
+                        AxisDirection::Positive => value.max(0.0),
+                        AxisDirection::Negative => (-value).max(0.0),
+                    })
+                    .fold(0.0, f32::max),
+            })
+            .fold(0.0, f32::max);
+
+        action_state.values.insert(*action, value);
+    }
+
+    // Nothing is considered just pressed while rebinding, including on the frame after the
+    // binding was captured
+    if rebinding.0.is_some() {
+        action_state.previous = action_state.values.clone();
+    }
+}
+
+fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    mut action_map: ResMut<ActionMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+
+    let axis_binding = || {
+        gamepads.iter().find_map(|gamepad| {
+            [
+                GamepadAxisType::LeftStickX,
+                GamepadAxisType::LeftStickY,
+                GamepadAxisType::RightStickX,
+                GamepadAxisType::RightStickY,
+            ]
+            .into_iter()
+            .find_map(|axis_type| {
+                let value = axes.get(GamepadAxis::new(gamepad, axis_type))?;
+                if value > REBIND_AXIS_THRESHOLD {
+                    Some(Binding::Axis(axis_type, AxisDirection::Positive))
+                } else if value < -REBIND_AXIS_THRESHOLD {
+                    Some(Binding::Axis(axis_type, AxisDirection::Negative))
+                } else {
+                    None
+                }
+            })
+        })
+    };
+
+    let binding = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Button(button.button_type))
+        })
+        .or_else(axis_binding);
+
+    if let Some(binding) = binding {
+        info!("Binding {action:?} to {binding:?}");
+        action_map.rebind(action, binding);
+        rebinding.0 = None;
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use self::asset::{
+    AnimationMode, AtlasLayoutAsset, AtlasLayoutLoader, SpriteAnimation, SpriteAnimationLoader,
+};
+
+pub mod asset;
+
+// Prevents looping forever on frames with a zero duration
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+pub struct SpriteAnimationPlugin;
+
+/// Plays a `SpriteAnimation` on the `TextureAtlasSprite` of the same entity.
+#[derive(Debug, Default, Component)]
+pub struct SpriteAnimator {
+    animation: Handle<SpriteAnimation>,
+    position: usize,
+    timer: f32,
+    backward: bool,
+    started: bool,
+    finished: bool,
+}
+
+impl SpriteAnimator {
+    pub fn new(animation: Handle<SpriteAnimation>) -> Self {
+        Self {
+            animation,
+            ..Default::default()
+        }
+    }
+
+    /// Starts the animation at `offset` seconds, useful to desynchronize identical sprites.
+    pub fn with_offset(mut self, offset: f32) -> Self {
+        self.timer = offset;
+        self
+    }
+
+    /// Switches to `animation`, restarting it only if it is not the one already playing.
+    pub fn play(&mut self, animation: &Handle<SpriteAnimation>) {
+        if self.animation != *animation {
+            *self = Self::new(animation.clone());
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct SpriteAnimationEvent {
+    pub entity: Entity,
+    pub name: Box<str>,
+}
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpriteAnimation>()
+            .init_asset::<AtlasLayoutAsset>()
+            .init_asset_loader::<SpriteAnimationLoader>()
+            .init_asset_loader::<AtlasLayoutLoader>()
+            .add_event::<SpriteAnimationEvent>()
+            .add_systems(PostUpdate, animate_sprites);
+    }
+}
+
+fn advance(animator: &mut SpriteAnimator, animation: &SpriteAnimation) {
+    let last = animation.frames.len() - 1;
+    match animation.mode {
+        AnimationMode::Loop => animator.position = (animator.position + 1) % (last + 1),
+        AnimationMode::Once if animator.position < last => animator.position += 1,
+        AnimationMode::Once => animator.finished = true,
+        AnimationMode::PingPong if last == 0 => {}
+        AnimationMode::PingPong => {
+            if animator.backward && animator.position == 0 {
+                animator.backward = false;
+            } else if !animator.backward && animator.position == last {
+                animator.backward = true;
+            }
+
+            if animator.backward {
+                animator.position -= 1;
+            } else {
+                animator.position += 1;
+            }
+        }
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    animations: Res<Assets<SpriteAnimation>>,
+    mut sprites: Query<(Entity, &mut SpriteAnimator, &mut TextureAtlasSprite)>,
+    mut events: EventWriter<SpriteAnimationEvent>,
+) {
+    for (entity, mut animator, mut sprite) in sprites.iter_mut() {
+        let Some(animation) = animations.get(&animator.animation) else {
+            continue;
+        };
+
+        if animation.frames.is_empty() || animator.finished {
+            continue;
+        }
+
+        let mut send_events = |position: usize| {
+            for (_, name) in animation
+                .events
+                .iter()
+                .filter(|(event_position, _)| *event_position == position)
+            {
+                events.send(SpriteAnimationEvent {
+                    entity,
+                    name: name.clone(),
+                });
+            }
+        };
+
+        if !animator.started {
+            animator.started = true;
+            send_events(animator.position);
+        }
+
+        animator.timer += time.delta_seconds();
+        while !animator.finished {
+            let duration = animation.frames[animator.position]
+                .1
+                .max(MIN_FRAME_DURATION);
+            if animator.timer < duration {
+                break;
+            }
+
+            animator.timer -= duration;
+            let previous = animator.position;
+            advance(&mut animator, animation);
+            if animator.position != previous {
+                send_events(animator.position);
+            }
+        }
+
+        let index = animation.frames[animator.position].0;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use thiserror::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum AnimationMode {
+    #[default]
+    Loop,
+    PingPong,
+    Once,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+enum FrameSpec {
+    Frame(usize, f32),
+    Range {
+        start: usize,
+        end: usize,
+        duration: f32,
+    },
+}
+
+/// A sequence of atlas indices with their durations in seconds. Events are fired when the
+/// animation reaches the given position in `frames`.
+#[derive(Debug, Default, TypePath, Asset)]
+pub struct SpriteAnimation {
+    pub frames: Vec<(usize, f32)>,
+    pub mode: AnimationMode,
+    pub events: Vec<(usize, Box<str>)>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SpriteAnimationFile {
+    frames: Vec<FrameSpec>,
+    #[serde(default)]
+    mode: AnimationMode,
+    #[serde(default)]
+    events: Vec<(usize, String)>,
+}
+
+impl From<SpriteAnimationFile> for SpriteAnimation {
+    fn from(file: SpriteAnimationFile) -> Self {
+        let frames = file
+            .frames
+            .into_iter()
+            .flat_map(|spec| match spec {
+                FrameSpec::Frame(index, duration) => vec![(index, duration)],
+                FrameSpec::Range {
+                    start,
+                    end,
+                    duration,
+                } => (start..=end).map(|index| (index, duration)).collect(),
+            })
+            .collect();
+
+        Self {
+            frames,
+            mode: file.mode,
+            events: file
+                .events
+                .into_iter()
+                .map(|(position, name)| (position, name.into()))
+                .collect(),
+        }
+    }
+}
+
+/// Describes how a sprite sheet is cut and which animations it contains. The texture atlas is
+/// available as the `atlas` labeled asset of the layout file and each clip as `clip/<name>`.
+#[derive(Debug, TypePath, Asset)]
+pub struct AtlasLayoutAsset {
+    pub atlas: Handle<TextureAtlas>,
+    pub clips: HashMap<String, Handle<SpriteAnimation>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtlasLayoutFile {
+    texture: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    clips: std::collections::HashMap<String, SpriteAnimationFile>,
+}
+
+#[derive(Debug, Error)]
+pub enum AnimationLoadError {
+    #[error("Could not load animation: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct SpriteAnimationLoader;
+
+impl AssetLoader for SpriteAnimationLoader {
+    type Asset = SpriteAnimation;
+
+    type Settings = ();
+
+    type Error = AnimationLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            let animation_file: SpriteAnimationFile = ron::de::from_bytes(&buf)?;
+
+            Ok(animation_file.into())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AtlasLayoutLoader;
+
+impl AssetLoader for AtlasLayoutLoader {
+    type Asset = AtlasLayoutAsset;
+
+    type Settings = ();
+
+    type Error = AnimationLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            let layout_file: AtlasLayoutFile = ron::de::from_bytes(&buf)?;
+
+            let texture = load_context.load(layout_file.texture);
+            let atlas = load_context.add_labeled_asset(
+                "atlas".to_string(),
+                TextureAtlas::from_grid(
+                    texture,
+                    Vec2::new(layout_file.tile_size.0, layout_file.tile_size.1),
+                    layout_file.columns,
+                    layout_file.rows,
+                    None,
+                    None,
+                ),
+            );
+
+            let clips = layout_file
+                .clips
+                .into_iter()
+                .map(|(name, clip)| {
+                    let handle = load_context
+                        .add_labeled_asset(format!("clip/{name}"), SpriteAnimation::from(clip));
+                    (name, handle)
+                })
+                .collect();
+
+            Ok(AtlasLayoutAsset { atlas, clips })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas.ron"]
+    }
+}
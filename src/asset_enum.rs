@@ -32,9 +32,16 @@ pub trait AssetEnum: Hash + Clone + Default + Sized + Send + Sync + PartialEq +
     fn get_list() -> &'static [(Self, &'static str)];
 }
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Resource)]
 pub struct AssetDictionary<T: Hash, A: Asset>(pub HashMap<T, Handle<A>>);
 
+// Not derived as it would require the keys and assets to implement `Default`
+impl<T: Hash, A: Asset> Default for AssetDictionary<T, A> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
 impl<T: Hash + PartialEq + Eq, A: Asset> AssetDictionary<T, A> {
     pub fn get<'a>(&self, key: &T, assets: &'a Assets<A>) -> Option<&'a A> {
         assets.get(self.0.get(key)?)
@@ -44,12 +51,17 @@ impl<T: Hash + PartialEq + Eq, A: Asset> AssetDictionary<T, A> {
     }
 }
     
-#[derive(Default)]
 pub struct AssetEnumPlugin<T: AssetEnum, A: Asset> {
     _data: PhantomData<(T, A)>
 }
 
-impl<T: AssetEnum, A: Asset + Default> Plugin for AssetEnumPlugin<T, A> {
+impl<T: AssetEnum, A: Asset> Default for AssetEnumPlugin<T, A> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+impl<T: AssetEnum, A: Asset> Plugin for AssetEnumPlugin<T, A> {
     fn build(&self, app: &mut App) {
         app.init_resource::<AssetDictionary<T, A>>()
             .add_systems(Startup, load_asset_enum::<T, A>);
@@ -0,0 +1,317 @@
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::asset_enum::{AssetDictionary, AssetEnumPlugin};
+use crate::dialog::CurrentDialog;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, CurrentRoom};
+use crate::player::movement::Thrusters;
+use crate::player::PlayerTag;
+use crate::AppState;
+
+pub use crate::audio::sounds::Sound;
+
+mod sounds;
+
+// Fraction of the volume gained or lost per second by crossfading music tracks
+const CROSSFADE_SPEED: f32 = 0.5;
+// Fraction of the volume gained or lost per second by the thruster hiss
+const THRUSTER_FADE_SPEED: f32 = 8.0;
+
+pub struct SoundPlugin;
+
+/// Volume buses, each one scaled by the master volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Dialog,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+pub struct VolumeSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub dialog: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            master: 0.8,
+            music: 0.6,
+            sfx: 1.0,
+            dialog: 1.0,
+        }
+    }
+}
+
+impl VolumeSettings {
+    pub fn volume(&self, bus: Bus) -> f32 {
+        self.master
+            * match bus {
+                Bus::Music => self.music,
+                Bus::Sfx => self.sfx,
+                Bus::Dialog => self.dialog,
+            }
+    }
+}
+
+/// A looping sound. Positional sounds fade out with the distance from the player and can't be
+/// heard beyond `range`.
+#[derive(Debug, Clone, Component)]
+pub struct SoundEmitter {
+    pub sound: Sound,
+    pub bus: Bus,
+    pub volume: f32,
+    pub range: Option<f32>,
+    /// Set by the owner of the sound to make it louder or quieter, 0.0 silencing it.
+    pub gain: f32,
+}
+
+impl SoundEmitter {
+    pub fn new(sound: Sound, bus: Bus) -> Self {
+        Self {
+            sound,
+            bus,
+            volume: 1.0,
+            range: None,
+            gain: 1.0,
+        }
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+}
+
+/// Plays a sound once, attenuated by the distance from the player when it has a position.
+#[derive(Debug, Clone, Event)]
+pub struct PlaySound {
+    pub sound: Sound,
+    pub bus: Bus,
+    /// Position of the sound and distance at which it can be heard.
+    pub position: Option<(Vec2, f32)>,
+}
+
+impl PlaySound {
+    pub fn new(sound: Sound, bus: Bus) -> Self {
+        Self {
+            sound,
+            bus,
+            position: None,
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+struct MusicTrack {
+    sound: Sound,
+    fade: f32,
+}
+
+fn attenuation(position: Vec2, listener: Option<Vec2>, range: f32) -> f32 {
+    let Some(listener) = listener else {
+        return 0.0;
+    };
+    let t = (1.0 - position.distance(listener) / range.max(0.001)).max(0.0);
+    t * t
+}
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        // Read from the settings when they are loaded first
+        app.init_resource::<VolumeSettings>()
+            .add_plugins(AssetEnumPlugin::<Sound, AudioSource>::default())
+            .add_event::<PlaySound>()
+            .add_systems(Startup, spawn_ambience)
+            .add_systems(
+                Update,
+                (
+                    (
+                        dialog_blip.run_if(in_state(AppState::InDialog)),
+                        play_sounds,
+                    )
+                        .chain(),
+                    thruster_hiss,
+                    crossfade_music,
+                    (start_emitters, update_emitters).chain(),
+                ),
+            );
+    }
+}
+
+fn spawn_ambience(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Ship ambience"),
+        SoundEmitter::new(Sound::ShipAmbience, Bus::Sfx).with_volume(0.4),
+    ));
+}
+
+/// Starts playing the emitters, silent until their volume is set.
+fn start_emitters(
+    mut commands: Commands,
+    sounds: Res<AssetDictionary<Sound, AudioSource>>,
+    emitters: Query<(Entity, &SoundEmitter), Without<Handle<AudioSource>>>,
+) {
+    for (entity, emitter) in emitters.iter() {
+        if let Some(source) = sounds.get_handle(&emitter.sound) {
+            commands.entity(entity).insert(AudioBundle {
+                source,
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+            });
+        }
+    }
+}
+
+fn update_emitters(
+    settings: Res<VolumeSettings>,
+    player: Query<&GlobalTransform, With<PlayerTag>>,
+    emitters: Query<(&SoundEmitter, &AudioSink, Option<&GlobalTransform>)>,
+) {
+    let listener = player
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation().truncate());
+
+    for (emitter, sink, transform) in emitters.iter() {
+        let distance_factor = match (emitter.range, transform) {
+            (Some(range), Some(transform)) => {
+                attenuation(transform.translation().truncate(), listener, range)
+            }
+            _ => 1.0,
+        };
+        sink.set_volume(
+            settings.volume(emitter.bus) * emitter.volume * emitter.gain * distance_factor,
+        );
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut events: EventReader<PlaySound>,
+    settings: Res<VolumeSettings>,
+    sounds: Res<AssetDictionary<Sound, AudioSource>>,
+    player: Query<&GlobalTransform, With<PlayerTag>>,
+) {
+    let listener = player
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation().truncate());
+
+    for event in events.read() {
+        let Some(source) = sounds.get_handle(&event.sound) else {
+            continue;
+        };
+        let volume = settings.volume(event.bus)
+            * event.position.map_or(1.0, |(position, range)| {
+                attenuation(position, listener, range)
+            });
+        if volume <= 0.0 {
+            continue;
+        }
+
+        commands.spawn(AudioBundle {
+            source,
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Despawn,
+                volume: Volume::new_relative(volume),
+                ..Default::default()
+            },
+        });
+    }
+}
+
+fn dialog_blip(dialog: Res<CurrentDialog>, mut sounds: EventWriter<PlaySound>) {
+    if dialog.is_changed() {
+        sounds.send(PlaySound::new(Sound::DialogBlip, Bus::Dialog));
+    }
+}
+
+/// Hisses while the thrusters burn, silent outside of the game.
+fn thruster_hiss(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut thrusters: Query<(&Thrusters, &mut SoundEmitter)>,
+) {
+    for (thrusters, mut emitter) in thrusters.iter_mut() {
+        let target = if *state.get() == AppState::InGame {
+            thrusters.burn
+        } else {
+            0.0
+        };
+        let step = THRUSTER_FADE_SPEED * time.delta_seconds();
+        emitter.gain += (target - emitter.gain).clamp(-step, step);
+    }
+}
+
+/// Fades in the music of the room the player is in, the one of the level outside of the rooms,
+/// and fades out the others.
+#[allow(clippy::too_many_arguments)]
+fn crossfade_music(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<VolumeSettings>,
+    sounds: Res<AssetDictionary<Sound, AudioSource>>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    current_room: Res<CurrentRoom>,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+) {
+    let music = levels.get(&level_asset.0).and_then(|level| {
+        current_room
+            .0
+            .as_ref()
+            .and_then(|room| level.room(room))
+            .and_then(|room| room.music)
+            .or(level.music)
+    });
+
+    if let Some(music) = music {
+        if !tracks.iter().any(|(_, track, _)| track.sound == music) {
+            if let Some(source) = sounds.get_handle(&music) {
+                commands.spawn((
+                    Name::new(format!("Music {music:?}")),
+                    AudioBundle {
+                        source,
+                        settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+                    },
+                    MusicTrack {
+                        sound: music,
+                        fade: 0.0,
+                    },
+                ));
+            }
+        }
+    }
+
+    let step = CROSSFADE_SPEED * time.delta_seconds();
+    for (entity, mut track, sink) in tracks.iter_mut() {
+        if Some(track.sound) == music {
+            track.fade = (track.fade + step).min(1.0);
+        } else {
+            track.fade -= step;
+            if track.fade <= 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        }
+
+        if let Some(sink) = sink {
+            sink.set_volume(settings.volume(Bus::Music) * track.fade);
+        }
+    }
+}
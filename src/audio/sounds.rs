@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::asset_enum::asset_enum_def;
+
+asset_enum_def!(
+    Sound,
+    SOUNDS,
+    [
+        (CabinMusic, "audio/music/cabin.wav"),
+        (VesselMusic, "audio/music/vessel.wav"),
+        (ShipAmbience, "audio/ambience/ship.wav"),
+        (ForceFieldHum, "audio/sfx/forcefield_hum.wav"),
+        (ThrusterHiss, "audio/sfx/thruster_hiss.wav"),
+        (DialogBlip, "audio/sfx/dialog_blip.wav"),
+        (Pickup, "audio/sfx/pickup.wav"),
+        (DoorSlide, "audio/sfx/door_slide.wav"),
+        (SwitchClick, "audio/sfx/switch_click.wav"),
+        (Hurt, "audio/sfx/hurt.wav"),
+        (ArcCrackle, "audio/sfx/arc_crackle.wav"),
+        (CreatureAlert, "audio/sfx/creature_alert.wav"),
+    ],
+    derive(Deserialize)
+);
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_rapier2d::prelude::*;
+
+use crate::tilemap::map_bounds;
+use crate::AppState;
+
+pub mod effects;
+
+pub struct CameraPlugin;
+
+/// Systems moving the camera, anything following the camera should run after them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct CameraSet;
+
+/// Marks the entity followed by the camera when nothing else is focused.
+#[derive(Debug, Component)]
+pub struct CameraTarget;
+
+/// Moves the camera towards its target, following it only once it leaves the dead-zone and
+/// keeping the view inside the map.
+#[derive(Debug, Component)]
+pub struct CameraController {
+    /// How fast the camera catches up with its target, higher is snappier.
+    pub smoothing: f32,
+    /// Seconds of the target velocity added to its position.
+    pub look_ahead: f32,
+    /// Half extents of the rectangle around the camera center in which the target can move
+    /// freely.
+    pub dead_zone: Vec2,
+    /// Beyond this distance the camera jumps to its target instead of panning, for respawns.
+    pub snap_distance: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            smoothing: 5.0,
+            look_ahead: 0.3,
+            dead_zone: Vec2::new(24.0, 16.0),
+            snap_distance: 400.0,
+        }
+    }
+}
+
+/// Entity temporarily followed by the camera instead of the `CameraTarget`, during dialogs for
+/// example.
+#[derive(Debug, Default, Resource)]
+pub struct CameraFocus(Option<Entity>);
+
+impl CameraFocus {
+    pub fn set(&mut self, entity: Entity) {
+        self.0 = Some(entity);
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFocus>()
+            // The state has already changed when leaving one, a dialog keeps its focus while the
+            // game is paused and loses it if the pause menu goes anywhere else
+            .add_systems(
+                OnExit(AppState::InDialog),
+                clear_focus.run_if(not(in_state(AppState::Paused))),
+            )
+            .add_systems(
+                OnExit(AppState::Paused),
+                clear_focus.run_if(not(in_state(AppState::InDialog))),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_target
+                    .in_set(CameraSet)
+                    .run_if(not(in_state(AppState::Cutscene)))
+                    .after(PhysicsSet::Writeback)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn clear_focus(mut focus: ResMut<CameraFocus>) {
+    focus.clear();
+}
+
+/// Moves `center` just enough for `target` to be back inside the dead-zone.
+fn apply_dead_zone(center: Vec2, target: Vec2, dead_zone: Vec2) -> Vec2 {
+    let offset = target - center;
+    center + offset - offset.clamp(-dead_zone, dead_zone)
+}
+
+fn clamp_to_bounds(center: Vec2, view: Vec2, bounds: Rect) -> Vec2 {
+    let half_view = view / 2.0;
+    let clamp_axis = |center: f32, half_view: f32, min: f32, max: f32| {
+        if max - min <= half_view * 2.0 {
+            (min + max) / 2.0
+        } else {
+            center.clamp(min + half_view, max - half_view)
+        }
+    };
+
+    Vec2::new(
+        clamp_axis(center.x, half_view.x, bounds.min.x, bounds.max.x),
+        clamp_axis(center.y, half_view.y, bounds.min.y, bounds.max.y),
+    )
+}
+
+fn follow_target(
+    time: Res<Time>,
+    focus: Res<CameraFocus>,
+    mut camera: Query<
+        (&CameraController, &OrthographicProjection, &mut Transform),
+        Without<CameraTarget>,
+    >,
+    target: Query<(&Transform, Option<&Velocity>), With<CameraTarget>>,
+    focused: Query<&GlobalTransform>,
+) {
+    let Ok((controller, projection, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+
+    let focused = focus.0.and_then(|entity| focused.get(entity).ok());
+    let goal = if let Some(focused) = focused {
+        focused.translation().truncate()
+    } else if let Ok((target, velocity)) = target.get_single() {
+        let look_ahead = velocity.map_or(Vec2::ZERO, |velocity| {
+            velocity.linvel * controller.look_ahead
+        });
+        let center = transform.translation.truncate();
+        apply_dead_zone(
+            center,
+            target.translation.truncate() + look_ahead,
+            controller.dead_zone,
+        )
+    } else {
+        return;
+    };
+
+    let current = transform.translation.truncate();
+    let position = if current.distance(goal) > controller.snap_distance {
+        goal
+    } else {
+        let t = 1.0 - (-controller.smoothing * time.delta_seconds()).exp();
+        current.lerp(goal, t)
+    };
+
+    let position = clamp_to_bounds(position, projection.area.size(), map_bounds());
+    transform.translation = position.extend(transform.translation.z);
+}
@@ -0,0 +1,326 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::player::PlayerTag;
+
+use super::{follow_target, CameraSet};
+
+// Shakes are dropped once their amplitude is below this, in pixels
+const MIN_SHAKE_AMPLITUDE: f32 = 0.1;
+// Speed above which hitting a wall shakes the camera
+const IMPACT_SPEED: f32 = 80.0;
+
+pub struct CameraEffectsPlugin;
+
+/// Feedback effects applied to the camera and the screen.
+#[derive(Debug, Clone, Event, Deserialize)]
+pub enum CameraEffect {
+    /// Shakes the camera by `amplitude` pixels `frequency` times per second, the amplitude being
+    /// divided by e every `1 / decay` seconds.
+    Shake {
+        amplitude: f32,
+        frequency: f32,
+        decay: f32,
+    },
+    /// Smoothly changes the scale of the projection, 1.0 being the default zoom and higher
+    /// values showing more of the level.
+    Zoom { scale: f32, duration: f32 },
+    /// Fades the screen to black, `to` being the final opacity.
+    Fade { to: f32, duration: f32 },
+    /// Fills the screen with `color` and fades it out over `duration`.
+    Flash {
+        color: (f32, f32, f32),
+        duration: f32,
+    },
+}
+
+#[derive(Debug, Component)]
+pub struct FadeOverlay;
+
+#[derive(Debug, Component)]
+struct FlashOverlay;
+
+#[derive(Debug, Clone, Copy)]
+struct Shake {
+    amplitude: f32,
+    frequency: f32,
+    decay: f32,
+    elapsed: f32,
+    phase: Vec2,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Tween {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl Tween {
+    fn new(from: f32, to: f32, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    fn advance(&mut self, delta: f32) -> f32 {
+        self.elapsed += delta;
+        let t = if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        };
+        self.from + (self.to - self.from) * t * t * (3.0 - 2.0 * t)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+struct ScreenEffects {
+    shakes: Vec<Shake>,
+    // Offset added to the camera by the last shake, removed before anything else moves it
+    shake_offset: Vec2,
+    zoom: Option<Tween>,
+    fade: Option<Tween>,
+    flash: Option<(Color, Tween)>,
+}
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraEffect>()
+            .init_resource::<ScreenEffects>()
+            .add_systems(Startup, setup_overlays)
+            .add_systems(PreUpdate, remove_shake)
+            .add_systems(
+                Update,
+                (impact_shake, start_effects, (zoom, fade, flash)).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_shake
+                    .in_set(CameraSet)
+                    .after(follow_target)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn setup_overlays(mut commands: Commands) {
+    let overlay = |z_index| NodeBundle {
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..Default::default()
+        },
+        z_index: ZIndex::Global(z_index),
+        ..Default::default()
+    };
+
+    commands.spawn((overlay(-2), FadeOverlay));
+    commands.spawn((overlay(-1), FlashOverlay));
+}
+
+fn start_effects(
+    mut events: EventReader<CameraEffect>,
+    mut effects: ResMut<ScreenEffects>,
+    projection: Query<&OrthographicProjection, With<Camera2d>>,
+    fade_overlay: Query<&BackgroundColor, With<FadeOverlay>>,
+) {
+    for event in events.read() {
+        match *event {
+            CameraEffect::Shake {
+                amplitude,
+                frequency,
+                decay,
+            } => {
+                // Desynchronizes the axes so the camera does not only shake diagonally
+                let seed = effects.shakes.len() as f32 + amplitude;
+                effects.shakes.push(Shake {
+                    amplitude,
+                    frequency,
+                    decay,
+                    elapsed: 0.0,
+                    phase: Vec2::new(seed.sin(), seed.cos()) * TAU,
+                });
+            }
+            CameraEffect::Zoom { scale, duration } => {
+                let from = projection
+                    .get_single()
+                    .map(|projection| projection.scale)
+                    .unwrap_or(1.0);
+                effects.zoom = Some(Tween::new(from, scale, duration));
+            }
+            CameraEffect::Fade { to, duration } => {
+                let from = fade_overlay
+                    .get_single()
+                    .map(|color| color.0.a())
+                    .unwrap_or_default();
+                effects.fade = Some(Tween::new(from, to, duration));
+            }
+            CameraEffect::Flash { color, duration } => {
+                let color = Color::rgb(color.0, color.1, color.2);
+                effects.flash = Some((color, Tween::new(1.0, 0.0, duration)));
+            }
+        }
+    }
+}
+
+fn zoom(
+    time: Res<Time>,
+    mut effects: ResMut<ScreenEffects>,
+    mut projection: Query<&mut OrthographicProjection, With<Camera2d>>,
+) {
+    let Some(tween) = effects.zoom.as_mut() else {
+        return;
+    };
+
+    let scale = tween.advance(time.delta_seconds());
+    if let Ok(mut projection) = projection.get_single_mut() {
+        projection.scale = scale;
+    }
+
+    if tween.is_finished() {
+        effects.zoom = None;
+    }
+}
+
+fn fade(
+    time: Res<Time>,
+    mut effects: ResMut<ScreenEffects>,
+    mut overlay: Query<&mut BackgroundColor, With<FadeOverlay>>,
+) {
+    let Some(tween) = effects.fade.as_mut() else {
+        return;
+    };
+
+    let alpha = tween.advance(time.delta_seconds());
+    if let Ok(mut color) = overlay.get_single_mut() {
+        color.0.set_a(alpha);
+    }
+
+    if tween.is_finished() {
+        effects.fade = None;
+    }
+}
+
+fn flash(
+    time: Res<Time>,
+    mut effects: ResMut<ScreenEffects>,
+    mut overlay: Query<&mut BackgroundColor, With<FlashOverlay>>,
+) {
+    let Some((color, tween)) = effects.flash.as_mut() else {
+        return;
+    };
+
+    let alpha = tween.advance(time.delta_seconds());
+    if let Ok(mut background) = overlay.get_single_mut() {
+        background.0 = color.with_a(alpha);
+    }
+
+    if tween.is_finished() {
+        effects.flash = None;
+    }
+}
+
+fn remove_shake(
+    mut effects: ResMut<ScreenEffects>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+) {
+    if effects.shake_offset == Vec2::ZERO {
+        return;
+    }
+
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation -= effects.shake_offset.extend(0.0);
+    }
+    effects.shake_offset = Vec2::ZERO;
+}
+
+fn apply_shake(
+    time: Res<Time>,
+    mut effects: ResMut<ScreenEffects>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+) {
+    if effects.shakes.is_empty() {
+        return;
+    }
+
+    let mut offset = Vec2::ZERO;
+    for shake in effects.shakes.iter_mut() {
+        shake.elapsed += time.delta_seconds();
+        let amplitude = shake.amplitude * (-shake.decay * shake.elapsed).exp();
+        let angle = shake.elapsed * shake.frequency * TAU;
+        offset += Vec2::new(
+            (angle + shake.phase.x).sin(),
+            (angle * 1.3 + shake.phase.y).sin(),
+        ) * amplitude;
+    }
+    effects.shakes.retain(|shake| {
+        shake.amplitude * (-shake.decay * shake.elapsed).exp() > MIN_SHAKE_AMPLITUDE
+    });
+
+    if let Ok(mut transform) = camera.get_single_mut() {
+        transform.translation += offset.extend(0.0);
+        effects.shake_offset = offset;
+    }
+}
+
+/// Shakes the camera when the player hits something at high speed.
+fn impact_shake(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut camera_effects: EventWriter<CameraEffect>,
+    player: Query<(Entity, &Velocity), With<PlayerTag>>,
+    parents: Query<&Parent>,
+    sensors: Query<(), With<Sensor>>,
+    // Collisions are reported after the physics step, so the speed of the previous frame is the
+    // one the player had before the impact
+    mut previous_speed: Local<f32>,
+) {
+    let Ok((player, velocity)) = player.get_single() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    let speed = *previous_speed;
+    *previous_speed = velocity.linvel.length();
+    if speed < IMPACT_SPEED {
+        collision_events.clear();
+        return;
+    }
+
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
+            let hit = [entity1, entity2].into_iter().any(&is_player)
+                && ![entity1, entity2]
+                    .into_iter()
+                    .any(|entity| sensors.contains(entity));
+            if hit {
+                camera_effects.send(CameraEffect::Shake {
+                    amplitude: (speed / IMPACT_SPEED * 2.0).min(6.0),
+                    frequency: 15.0,
+                    decay: 6.0,
+                });
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_rapier2d::prelude::*;
+
+use crate::dialog::Dialog;
+use crate::inventory::Inventory;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::objects::FiredTriggers;
+use crate::player::PlayerTag;
+use crate::story::StoryFlags;
+use crate::ResetEvent;
+
+pub struct CheckpointPlugin;
+
+#[derive(Debug, Component)]
+pub struct Checkpoint(pub String);
+
+/// The last checkpoint reached by the player and the state of the world at that moment.
+#[derive(Debug, Default, Resource)]
+pub struct LastCheckpoint {
+    /// `None` until a checkpoint of the level is reached.
+    pub name: Option<String>,
+    /// Where the player respawns, the start of the level when `None`.
+    pub position: Option<Vec3>,
+    pub fired_triggers: HashSet<Dialog>,
+    pub story_flags: StoryFlags,
+    pub inventory: Inventory,
+}
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastCheckpoint>()
+            .add_systems(Update, spawn_checkpoints.after(SpawnLevelSet))
+            .add_systems(Update, (checkpoint_check, respawn_at_checkpoint));
+    }
+}
+
+/// Spawns the checkpoints of the current level each time it is built.
+fn spawn_checkpoints(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+
+    for spec in level.checkpoints.iter() {
+        commands.spawn((
+            Name::new(format!("Checkpoint {}", spec.name)),
+            SpatialBundle::from_transform(Transform::from_xyz(
+                spec.position.0,
+                spec.position.1,
+                0.0,
+            )),
+            Collider::ball(16.0),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            Checkpoint(spec.name.clone()),
+            LevelEntity,
+        ));
+    }
+}
+
+fn checkpoint_check(
+    mut collision_events: EventReader<CollisionEvent>,
+    checkpoints: Query<(&Checkpoint, &Transform)>,
+    player: Query<(Entity, &Inventory), With<PlayerTag>>,
+    parents: Query<&Parent>,
+    fired_triggers: Res<FiredTriggers>,
+    story_flags: Res<StoryFlags>,
+    mut last_checkpoint: ResMut<LastCheckpoint>,
+) {
+    let Ok((player, inventory)) = player.get_single() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
+            let checkpoint = if is_player(entity2) {
+                checkpoints.get(entity1)
+            } else if is_player(entity1) {
+                checkpoints.get(entity2)
+            } else {
+                continue;
+            };
+
+            if let Ok((Checkpoint(name), transform)) = checkpoint {
+                if last_checkpoint.name.as_ref() != Some(name) {
+                    info!("Reached checkpoint {name}");
+                }
+                *last_checkpoint = LastCheckpoint {
+                    name: Some(name.clone()),
+                    position: Some(transform.translation.truncate().extend(1.0)),
+                    fired_triggers: fired_triggers.0.clone(),
+                    story_flags: story_flags.clone(),
+                    inventory: inventory.clone(),
+                };
+            }
+        }
+    }
+}
+
+fn respawn_at_checkpoint(
+    mut events: EventReader<ResetEvent>,
+    mut player: Query<(&mut Transform, &mut Velocity, &mut Inventory), With<PlayerTag>>,
+    mut fired_triggers: ResMut<FiredTriggers>,
+    mut story_flags: ResMut<StoryFlags>,
+    last_checkpoint: Res<LastCheckpoint>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    for _ in events.read() {
+        let Ok((mut transform, mut velocity, mut inventory)) = player.get_single_mut() else {
+            continue;
+        };
+
+        if let Some(position) = last_checkpoint.position {
+            transform.translation = position;
+        } else if let Some(level) = levels.get(&level_asset.0) {
+            transform.translation = level.start_position().extend(transform.translation.z);
+        }
+        *velocity = Default::default();
+        fired_triggers.0 = last_checkpoint.fired_triggers.clone();
+        *story_flags = last_checkpoint.story_flags.clone();
+        // Items picked up since the checkpoint are dropped, their pickups spawn again
+        *inventory = last_checkpoint.inventory.clone();
+    }
+}
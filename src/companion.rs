@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::dialog::bubble::ShowBubble;
+use crate::inventory::Inventory;
+use crate::level::asset::LevelAsset;
+use crate::level::CurrentLevelAsset;
+use crate::lighting::{LightAnimation, PointLight2d};
+use crate::objects::FiredTriggers;
+use crate::player::PlayerTag;
+use crate::AppState;
+
+// Position of the drone next to the player, on the side opposite to where the player heads
+const FOLLOW_OFFSET: Vec2 = Vec2::new(14.0, 14.0);
+const MAX_SPEED: f32 = 110.0;
+const MAX_FORCE: f32 = 300.0;
+// Distance from its target at which the drone starts slowing down
+const SLOWING_RADIUS: f32 = 40.0;
+// Rays cast ahead of the drone to steer away from the walls
+const FEELER_LENGTH: f32 = 20.0;
+const FEELER_ANGLE: f32 = 0.5;
+const AVOID_FORCE: f32 = 600.0;
+// Beyond this distance the drone jumps back next to the player, after a reset or a load
+const TELEPORT_DISTANCE: f32 = 300.0;
+const BOB_AMPLITUDE: f32 = 1.5;
+const BOB_SPEED: f32 = 2.5;
+
+pub struct CompanionPlugin;
+
+/// The AI of the dialogs, floating next to the player in a drone.
+#[derive(Debug, Default, Component)]
+pub struct Companion {
+    velocity: Vec2,
+    /// 1.0 to stay on the right of the player, -1.0 on the left.
+    side: f32,
+}
+
+#[derive(Debug, Component)]
+struct DroneSprite;
+
+impl Plugin for CompanionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_companion).add_systems(
+            Update,
+            (
+                steer_companion,
+                bob_drone,
+                companion_barks.run_if(in_state(AppState::InGame)),
+            ),
+        );
+    }
+}
+
+fn spawn_companion(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            Name::new("AI drone"),
+            // Jumps next to the player once it is placed in the level
+            SpatialBundle::from_transform(Transform::from_translation(FOLLOW_OFFSET.extend(1.5))),
+            Companion {
+                velocity: Vec2::ZERO,
+                side: 1.0,
+            },
+            PointLight2d {
+                color: Color::rgb(0.5, 0.8, 1.0),
+                radius: 40.0,
+                intensity: 0.5,
+                animation: LightAnimation::Pulse {
+                    period: 2.0,
+                    amount: 0.3,
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                SpriteBundle {
+                    texture: asset_server.load("texture/drone.png"),
+                    ..Default::default()
+                },
+                DroneSprite,
+            ));
+        });
+}
+
+/// Arrives next to the player while the feelers push the drone away from the walls ahead.
+fn steer_companion(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    player: Query<(&Transform, &Velocity), With<PlayerTag>>,
+    mut companion: Query<(&mut Companion, &mut Transform), Without<PlayerTag>>,
+) {
+    let delta = time.delta_seconds();
+    let (Ok((player, player_velocity)), Ok((mut companion, mut transform))) =
+        (player.get_single(), companion.get_single_mut())
+    else {
+        return;
+    };
+    if delta == 0.0 {
+        return;
+    }
+
+    if player_velocity.linvel.x.abs() > 10.0 {
+        companion.side = -player_velocity.linvel.x.signum();
+    }
+    let target = player.translation.truncate()
+        + Vec2::new(FOLLOW_OFFSET.x * companion.side, FOLLOW_OFFSET.y);
+    let position = transform.translation.truncate();
+    let to_target = target - position;
+
+    if to_target.length() > TELEPORT_DISTANCE {
+        companion.velocity = Vec2::ZERO;
+        transform.translation = target.extend(transform.translation.z);
+        return;
+    }
+
+    let speed = MAX_SPEED * (to_target.length() / SLOWING_RADIUS).min(1.0);
+    let desired = to_target.normalize_or_zero() * speed;
+    let mut steering = (desired - companion.velocity).clamp_length_max(MAX_FORCE);
+
+    // Only the walls, doors and fields are avoided, not the player or the sensors
+    let filter = QueryFilter::new().exclude_sensors().exclude_dynamic();
+    let heading = companion
+        .velocity
+        .try_normalize()
+        .unwrap_or(to_target.normalize_or_zero());
+    if heading != Vec2::ZERO {
+        for angle in [0.0, FEELER_ANGLE, -FEELER_ANGLE] {
+            let direction = Vec2::from_angle(angle).rotate(heading);
+            if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+                position,
+                direction,
+                FEELER_LENGTH,
+                true,
+                filter,
+            ) {
+                steering += hit.normal * AVOID_FORCE * (1.0 - hit.toi / FEELER_LENGTH);
+            }
+        }
+    }
+
+    let mut velocity = (companion.velocity + steering * delta).clamp_length_max(MAX_SPEED);
+    // Slides along the walls the steering could not avoid in time
+    if velocity != Vec2::ZERO {
+        if let Some((_, hit)) =
+            rapier_context.cast_ray_and_get_normal(position, velocity, delta, true, filter)
+        {
+            velocity -= hit.normal * velocity.dot(hit.normal).min(0.0);
+        }
+    }
+
+    companion.velocity = velocity;
+    transform.translation += (velocity * delta).extend(0.0);
+}
+
+fn bob_drone(time: Res<Time>, mut sprites: Query<&mut Transform, With<DroneSprite>>) {
+    for mut transform in sprites.iter_mut() {
+        transform.translation.y = (time.elapsed_seconds() * BOB_SPEED).sin() * BOB_AMPLITUDE;
+    }
+}
+
+/// Comments on the points of interest of the level the player comes near, once each.
+fn companion_barks(
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    player: Query<(&Transform, &Inventory), With<PlayerTag>>,
+    companion: Query<Entity, With<Companion>>,
+    mut fired_triggers: ResMut<FiredTriggers>,
+    mut bubbles: EventWriter<ShowBubble>,
+) {
+    let (Some(level), Ok((player, inventory)), Ok(companion)) = (
+        levels.get(&level_asset.0),
+        player.get_single(),
+        companion.get_single(),
+    ) else {
+        return;
+    };
+
+    for point in level.points_of_interest.iter() {
+        let position = Vec2::new(point.position.0, point.position.1);
+        if fired_triggers.0.contains(&point.bark)
+            || player.translation.truncate().distance(position) > point.radius
+            || !point
+                .requires
+                .as_ref()
+                .is_none_or(|check| check.check(inventory))
+        {
+            continue;
+        }
+
+        fired_triggers.0.insert(point.bark);
+        bubbles.send(ShowBubble {
+            speaker: companion,
+            dialog: point.bark,
+        });
+    }
+}
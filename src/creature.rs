@@ -0,0 +1,438 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::{Bus, PlaySound, Sound};
+use crate::health::DamageEvent;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::lighting::PointLight2d;
+use crate::player::movement::Thrusters;
+use crate::player::PlayerTag;
+use crate::{AppState, ResetEvent};
+
+use self::asset::{BehaviourState, Condition, CreatureAsset, CreatureLoader};
+use self::navigation::{update_obstacles, NavGrid};
+
+pub mod asset;
+mod navigation;
+
+// Distance at which a point of the path counts as reached
+const WAYPOINT_RADIUS: f32 = 3.0;
+// Seconds between two paths towards the player while chasing it
+const CHASE_REPATH: f32 = 0.5;
+// Cells searched around a fleeing creature for the spot farthest from the player
+const FLEE_STEPS: u32 = 12;
+// Speed at which idle creatures look around, in radians per second
+const LOOK_AROUND_SPEED: f32 = 1.2;
+const ALERT_RANGE: f32 = 200.0;
+
+pub struct CreaturePlugin;
+
+/// A creature of the level, driven by the state machine of its `.creature.ron` file.
+#[derive(Debug, Component)]
+pub struct Creature {
+    definition: Handle<CreatureAsset>,
+    pub state: BehaviourState,
+    home: Vec2,
+    patrol: Vec<Vec2>,
+    patrol_index: usize,
+    /// Points left to go through, the next one last.
+    path: Vec<Vec2>,
+    arrived: bool,
+    heading: Vec2,
+    time_in_state: f32,
+    /// Seconds since the player was last seen, and where it was last seen or heard.
+    since_seen: f32,
+    last_known: Option<Vec2>,
+    repath: Timer,
+    /// The definition has been applied to the entity.
+    ready: bool,
+}
+
+impl Creature {
+    fn new(definition: Handle<CreatureAsset>, home: Vec2, patrol: Vec<Vec2>) -> Self {
+        Self {
+            definition,
+            state: BehaviourState::default(),
+            home,
+            patrol,
+            patrol_index: 0,
+            path: Vec::new(),
+            arrived: false,
+            heading: Vec2::X,
+            time_in_state: 0.0,
+            since_seen: f32::INFINITY,
+            last_known: None,
+            repath: Timer::from_seconds(CHASE_REPATH, TimerMode::Repeating),
+            ready: false,
+        }
+    }
+
+    fn enter(&mut self, state: BehaviourState) {
+        self.state = state;
+        self.path.clear();
+        self.arrived = false;
+        self.time_in_state = 0.0;
+    }
+
+    /// Ends the current path, patrolling creatures head to the next point the next time.
+    fn arrive(&mut self) {
+        self.arrived = true;
+        if self.state == BehaviourState::Patrol && !self.patrol.is_empty() {
+            self.patrol_index = (self.patrol_index + 1) % self.patrol.len();
+        }
+    }
+}
+
+impl Plugin for CreaturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CreatureAsset>()
+            .init_asset_loader::<CreatureLoader>()
+            .init_resource::<NavGrid>()
+            .add_systems(
+                Update,
+                (spawn_creatures, apply_definitions)
+                    .chain()
+                    .after(SpawnLevelSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    reset_creatures,
+                    (
+                        think,
+                        update_obstacles,
+                        plan_paths,
+                        move_creatures,
+                        creature_contact,
+                    )
+                        .chain()
+                        .run_if(in_state(AppState::InGame)),
+                )
+                    .chain()
+                    .after(apply_definitions),
+            )
+            .add_systems(OnExit(AppState::InGame), stop_creatures);
+    }
+}
+
+/// Spawns the creatures of the current level each time it is built.
+fn spawn_creatures(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    asset_server: Res<AssetServer>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+    let position = |(x, y): (f32, f32)| Vec2::new(x, y);
+
+    for spec in level.creatures.iter() {
+        commands.spawn((
+            Name::new(spec.name.clone()),
+            SpriteBundle {
+                transform: Transform::from_translation(position(spec.position).extend(0.4)),
+                ..Default::default()
+            },
+            Creature::new(
+                asset_server.load(spec.creature.clone()),
+                position(spec.position),
+                spec.patrol.iter().copied().map(position).collect(),
+            ),
+            RigidBody::Dynamic,
+            Velocity::default(),
+            LockedAxes::ROTATION_LOCKED,
+            Friction::coefficient(0.0),
+            ColliderMassProperties::Density(5.0),
+            ActiveEvents::COLLISION_EVENTS,
+            LevelEntity,
+        ));
+    }
+}
+
+/// Gives the creatures the look and the senses of their definition once it is loaded, and again
+/// when it is modified.
+fn apply_definitions(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<CreatureAsset>>,
+    asset_server: Res<AssetServer>,
+    definitions: Res<Assets<CreatureAsset>>,
+    mut creatures: Query<(Entity, &mut Creature, &mut Handle<Image>)>,
+) {
+    let events: Vec<_> = events.read().collect();
+
+    for (entity, mut creature, mut texture) in creatures.iter_mut() {
+        if events
+            .iter()
+            .any(|event| event.is_modified(&creature.definition))
+        {
+            creature.ready = false;
+        }
+        if creature.ready {
+            continue;
+        }
+        let Some(definition) = definitions.get(&creature.definition) else {
+            continue;
+        };
+
+        *texture = asset_server.load(definition.texture.clone());
+        let mut entity = commands.entity(entity);
+        entity.insert(Collider::ball(definition.radius));
+        match definition.eye_color {
+            Some((r, g, b)) => entity.insert(PointLight2d {
+                color: Color::rgb(r, g, b),
+                radius: definition.radius * 3.0,
+                intensity: 0.4,
+                ..Default::default()
+            }),
+            None => entity.remove::<PointLight2d>(),
+        };
+        creature.enter(definition.initial);
+        creature.ready = true;
+    }
+}
+
+/// Looks and listens for the player, then follows the first transition of the current state
+/// whose condition holds.
+fn think(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    definitions: Res<Assets<CreatureAsset>>,
+    player: Query<(&Transform, &Thrusters), With<PlayerTag>>,
+    mut creatures: Query<(&mut Creature, &Transform), Without<PlayerTag>>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    let Ok((player, thrusters)) = player.get_single() else {
+        return;
+    };
+    let player_position = player.translation.truncate();
+    // Sight lines are only blocked by the walls, doors and fields
+    let filter = QueryFilter::new().exclude_sensors().exclude_dynamic();
+
+    for (mut creature, transform) in creatures.iter_mut() {
+        let Some(definition) = definitions.get(&creature.definition) else {
+            continue;
+        };
+        if !creature.ready {
+            continue;
+        }
+
+        creature.time_in_state += time.delta_seconds();
+        creature.since_seen += time.delta_seconds();
+
+        let position = transform.translation.truncate();
+        let to_player = player_position - position;
+        let distance = to_player.length();
+        let sees = distance > 0.0
+            && distance <= definition.sight_range
+            && creature.heading.angle_between(to_player).abs() <= definition.sight_angle
+            && rapier_context
+                .cast_ray(position, to_player / distance, distance, true, filter)
+                .is_none();
+        let hears = distance <= definition.hearing_range && thrusters.burn > 0.0;
+        if sees {
+            creature.since_seen = 0.0;
+        }
+        if sees || hears {
+            creature.last_known = Some(player_position);
+        }
+
+        let holds = |condition: Condition| match condition {
+            Condition::SeesPlayer => sees,
+            Condition::HearsPlayer => hears,
+            Condition::LostPlayer(seconds) => creature.since_seen > seconds,
+            Condition::PlayerWithin(range) => distance <= range,
+            Condition::PlayerBeyond(range) => distance > range,
+            Condition::Arrived => creature.arrived,
+            Condition::After(seconds) => creature.time_in_state > seconds,
+        };
+        let next = definition
+            .states
+            .get(&creature.state)
+            .and_then(|spec| {
+                spec.transitions
+                    .iter()
+                    .find(|transition| holds(transition.when))
+            })
+            .map(|transition| transition.to);
+
+        if let Some(next) = next.filter(|&next| next != creature.state) {
+            debug!("Creature goes from {:?} to {:?}", creature.state, next);
+            if next == BehaviourState::Chase {
+                sounds.send(PlaySound {
+                    sound: Sound::CreatureAlert,
+                    bus: Bus::Sfx,
+                    position: Some((position, ALERT_RANGE)),
+                });
+            }
+            creature.enter(next);
+        }
+    }
+}
+
+/// Finds a path on the grid of the map towards the destination of the current state.
+fn plan_paths(
+    time: Res<Time>,
+    grid: Res<NavGrid>,
+    player: Query<&Transform, With<PlayerTag>>,
+    mut creatures: Query<(&mut Creature, &Transform), Without<PlayerTag>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let player_position = player.translation.truncate();
+
+    for (mut creature, transform) in creatures.iter_mut() {
+        let repath = creature.repath.tick(time.delta()).just_finished();
+        let needs_path = match creature.state {
+            BehaviourState::Idle => false,
+            BehaviourState::Chase => repath || (creature.path.is_empty() && !creature.arrived),
+            _ => creature.path.is_empty() && !creature.arrived,
+        };
+        if !needs_path {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let destination = match creature.state {
+            BehaviourState::Idle => None,
+            BehaviourState::Patrol => Some(
+                creature
+                    .patrol
+                    .get(creature.patrol_index)
+                    .copied()
+                    .unwrap_or(creature.home),
+            ),
+            BehaviourState::Investigate => creature.last_known,
+            BehaviourState::Chase => Some(player_position),
+            BehaviourState::Flee => grid.farthest_from(position, player_position, FLEE_STEPS),
+        };
+
+        // Without a way there the creature gives up and counts as arrived
+        let mut path = destination
+            .and_then(|destination| grid.find_path(position, destination))
+            .unwrap_or_default();
+        path.reverse();
+        // The first point is the cell the creature stands in
+        path.pop();
+        if path.is_empty() {
+            creature.arrive();
+        }
+        creature.path = path;
+    }
+}
+
+fn move_creatures(
+    time: Res<Time>,
+    definitions: Res<Assets<CreatureAsset>>,
+    mut creatures: Query<(&mut Creature, &Transform, &mut Velocity, &mut Sprite)>,
+) {
+    for (mut creature, transform, mut velocity, mut sprite) in creatures.iter_mut() {
+        let Some(definition) = definitions.get(&creature.definition) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        while creature
+            .path
+            .last()
+            .is_some_and(|next| next.distance(position) < WAYPOINT_RADIUS)
+        {
+            creature.path.pop();
+            if creature.path.is_empty() {
+                creature.arrive();
+            }
+        }
+
+        velocity.linvel = creature.path.last().map_or(Vec2::ZERO, |next| {
+            (*next - position).normalize_or_zero() * definition.speed(creature.state)
+        });
+
+        if velocity.linvel != Vec2::ZERO {
+            creature.heading = velocity.linvel.normalize();
+        } else if creature.state == BehaviourState::Idle {
+            creature.heading =
+                Vec2::from_angle(LOOK_AROUND_SPEED * time.delta_seconds()).rotate(creature.heading);
+        }
+        sprite.flip_x = creature.heading.x < 0.0;
+    }
+}
+
+fn creature_contact(
+    mut collision_events: EventReader<CollisionEvent>,
+    definitions: Res<Assets<CreatureAsset>>,
+    creatures: Query<&Creature>,
+    player: Query<Entity, With<PlayerTag>>,
+    parents: Query<&Parent>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
+            let creature = if is_player(entity2) {
+                creatures.get(entity1)
+            } else if is_player(entity1) {
+                creatures.get(entity2)
+            } else {
+                continue;
+            };
+
+            if let Some(definition) = creature
+                .ok()
+                .and_then(|creature| definitions.get(&creature.definition))
+            {
+                if definition.contact_damage > 0.0 {
+                    damage.send(DamageEvent {
+                        amount: definition.contact_damage,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Sends the creatures back home in their initial state.
+fn reset_creatures(
+    mut events: EventReader<ResetEvent>,
+    definitions: Res<Assets<CreatureAsset>>,
+    mut creatures: Query<(&mut Creature, &mut Transform, &mut Velocity)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    for (mut creature, mut transform, mut velocity) in creatures.iter_mut() {
+        transform.translation = creature.home.extend(transform.translation.z);
+        *velocity = Velocity::zero();
+        creature.patrol_index = 0;
+        creature.since_seen = f32::INFINITY;
+        creature.last_known = None;
+        let initial = definitions
+            .get(&creature.definition)
+            .map_or(BehaviourState::default(), |definition| definition.initial);
+        creature.enter(initial);
+    }
+}
+
+/// The creatures wait in place while the game is not running.
+fn stop_creatures(mut creatures: Query<&mut Velocity, With<Creature>>) {
+    for mut velocity in creatures.iter_mut() {
+        *velocity = Velocity::zero();
+    }
+}
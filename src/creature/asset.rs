@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum BehaviourState {
+    /// Stays in place and looks around.
+    #[default]
+    Idle,
+    /// Goes from one patrol point of the level to the next.
+    Patrol,
+    /// Goes where the player was last seen or heard.
+    Investigate,
+    /// Runs after the player.
+    Chase,
+    /// Runs away from the player.
+    Flee,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Condition {
+    /// The player is in the field of view and no wall is in between.
+    SeesPlayer,
+    /// The player burns its thrusters within the hearing range.
+    HearsPlayer,
+    /// The player has not been seen for this many seconds.
+    LostPlayer(f32),
+    PlayerWithin(f32),
+    PlayerBeyond(f32),
+    /// The creature reached the end of its path.
+    Arrived,
+    /// The creature has been in its state for this many seconds.
+    After(f32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transition {
+    pub when: Condition,
+    pub to: BehaviourState,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StateSpec {
+    pub speed: f32,
+    /// Checked in order, the first one whose condition holds changes the state.
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/// The look, the senses and the behaviour of a creature read from a `.creature.ron` file.
+#[derive(Debug, Deserialize, TypePath, Asset)]
+pub struct CreatureAsset {
+    pub texture: String,
+    pub radius: f32,
+    #[serde(default)]
+    pub eye_color: Option<(f32, f32, f32)>,
+    pub sight_range: f32,
+    /// Half of the angle of the field of view, in radians.
+    pub sight_angle: f32,
+    pub hearing_range: f32,
+    /// Health lost by the player touching the creature.
+    #[serde(default)]
+    pub contact_damage: f32,
+    pub initial: BehaviourState,
+    pub states: HashMap<BehaviourState, StateSpec>,
+}
+
+impl CreatureAsset {
+    /// Speed of the creature in a state, states left out of the file keep it in place.
+    pub fn speed(&self, state: BehaviourState) -> f32 {
+        self.states.get(&state).map_or(0.0, |spec| spec.speed)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CreatureLoadError {
+    #[error("Could not load creature: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct CreatureLoader;
+
+impl AssetLoader for CreatureLoader {
+    type Asset = CreatureAsset;
+
+    type Settings = ();
+
+    type Error = CreatureLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            Ok(ron::de::from_bytes(&buf)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["creature.ron"]
+    }
+}
@@ -0,0 +1,283 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::forcefield::ForceField;
+use crate::machinery::Door;
+use crate::tilemap::{map_bounds, wall_rects};
+
+const CELL_SIZE: f32 = 8.0;
+// Distance kept between the center of the creatures and the walls
+const CLEARANCE: f32 = 6.0;
+// Costs of the moves between cells, diagonals being about sqrt(2) times longer
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+// Cells searched around a position inside a wall for the closest free one
+const SNAP_RADIUS: i32 = 3;
+
+type Cell = (i32, i32);
+
+/// Grid of the cells of the map the creatures can stand in, built from the walls of the tiles
+/// and the closed doors and active forcefields.
+#[derive(Debug, Resource)]
+pub struct NavGrid {
+    origin: Vec2,
+    width: i32,
+    height: i32,
+    /// The walls and obstacles grown by the clearance.
+    walls: Vec<Rect>,
+    obstacles: Vec<Rect>,
+    walkable: Vec<bool>,
+}
+
+impl Default for NavGrid {
+    fn default() -> Self {
+        Self::new(map_bounds(), &wall_rects())
+    }
+}
+
+impl NavGrid {
+    pub fn new(bounds: Rect, walls: &[Rect]) -> Self {
+        let mut grid = Self {
+            origin: bounds.min,
+            width: (bounds.width() / CELL_SIZE).ceil() as i32,
+            height: (bounds.height() / CELL_SIZE).ceil() as i32,
+            walls: walls.iter().map(|wall| wall.inset(CLEARANCE)).collect(),
+            obstacles: Vec::new(),
+            walkable: Vec::new(),
+        };
+        grid.rebuild();
+        grid
+    }
+
+    /// Replaces the obstacles blocking the cells on top of the walls, only rebuilding the grid
+    /// when they changed.
+    pub fn set_obstacles(&mut self, obstacles: &[Rect]) {
+        let obstacles: Vec<Rect> = obstacles
+            .iter()
+            .map(|obstacle| obstacle.inset(CLEARANCE))
+            .collect();
+        if obstacles != self.obstacles {
+            self.obstacles = obstacles;
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.walkable = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|cell| {
+                let center = self.center(cell);
+                !self
+                    .walls
+                    .iter()
+                    .chain(self.obstacles.iter())
+                    .any(|wall| wall.contains(center))
+            })
+            .collect();
+    }
+
+    fn cell(&self, position: Vec2) -> Cell {
+        let cell = ((position - self.origin) / CELL_SIZE).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    fn center(&self, (x, y): Cell) -> Vec2 {
+        self.origin + (Vec2::new(x as f32, y as f32) + 0.5) * CELL_SIZE
+    }
+
+    fn is_walkable(&self, (x, y): Cell) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width
+            && y < self.height
+            && self.walkable[(y * self.width + x) as usize]
+    }
+
+    /// The closest walkable cell around a position, which can be inside the clearance of a wall.
+    fn snap(&self, position: Vec2) -> Option<Cell> {
+        let (x, y) = self.cell(position);
+        (0..=SNAP_RADIUS).find_map(|radius| {
+            (-radius..=radius)
+                .flat_map(|dx| (-radius..=radius).map(move |dy| (x + dx, y + dy)))
+                .filter(|&cell| self.is_walkable(cell))
+                .min_by(|a, b| {
+                    let a = self.center(*a).distance_squared(position);
+                    let b = self.center(*b).distance_squared(position);
+                    a.total_cmp(&b)
+                })
+        })
+    }
+
+    /// Walkable cells next to a cell, without cutting the corners of the walls.
+    fn neighbours(&self, (x, y): Cell) -> impl Iterator<Item = (Cell, u32)> + '_ {
+        [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ]
+        .into_iter()
+        .filter(move |&(dx, dy)| {
+            self.is_walkable((x + dx, y + dy))
+                && self.is_walkable((x + dx, y))
+                && self.is_walkable((x, y + dy))
+        })
+        .map(move |(dx, dy)| {
+            let cost = if dx != 0 && dy != 0 {
+                DIAGONAL_COST
+            } else {
+                STRAIGHT_COST
+            };
+            ((x + dx, y + dy), cost)
+        })
+    }
+
+    /// Shortest path between two positions with A*, as the centers of the cells to go through.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.snap(from)?;
+        let goal = self.snap(to)?;
+        let heuristic = |(x, y): Cell| {
+            let (dx, dy) = ((x - goal.0).unsigned_abs(), (y - goal.1).unsigned_abs());
+            STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+        };
+
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), start))]);
+        let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+        let mut costs: HashMap<Cell, u32> = HashMap::from([(start, 0)]);
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = vec![self.center(cell)];
+                let mut cell = cell;
+                while let Some(&previous) = came_from.get(&cell) {
+                    path.push(self.center(previous));
+                    cell = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let cost = costs[&cell];
+            for (next, step) in self.neighbours(cell) {
+                let next_cost = cost + step;
+                if costs.get(&next).is_none_or(|&known| next_cost < known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The reachable position within `steps` cells of `from` that is the farthest from `threat`.
+    pub fn farthest_from(&self, from: Vec2, threat: Vec2, steps: u32) -> Option<Vec2> {
+        let start = self.snap(from)?;
+        let mut queue = VecDeque::from([(start, 0)]);
+        let mut visited = HashSet::from([start]);
+        let mut best = (start, self.center(start).distance_squared(threat));
+
+        while let Some((cell, depth)) = queue.pop_front() {
+            let distance = self.center(cell).distance_squared(threat);
+            if distance > best.1 {
+                best = (cell, distance);
+            }
+            if depth == steps {
+                continue;
+            }
+            for (next, _) in self.neighbours(cell) {
+                if visited.insert(next) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+
+        Some(self.center(best.0))
+    }
+}
+
+/// Blocks the cells under the closed doors and the active forcefields.
+pub fn update_obstacles(
+    mut grid: ResMut<NavGrid>,
+    doors: Query<(&GlobalTransform, &Sprite, &Door)>,
+    fields: Query<(&GlobalTransform, &ForceField)>,
+) {
+    let doors = doors
+        .iter()
+        .filter(|(_, _, door)| !door.open)
+        .map(|(transform, sprite, _)| {
+            let size = sprite.custom_size.unwrap_or_default();
+            Rect::from_center_size(transform.translation().truncate(), size)
+        });
+    let fields = fields
+        .iter()
+        .filter(|(_, field)| field.enabled)
+        .map(|(transform, field)| {
+            // Bounding box of the rotated field
+            let (_, rotation, translation) = transform.to_scale_rotation_translation();
+            let half_size = field.size / 2.0;
+            let x = (rotation * Vec3::X * half_size.x).truncate().abs();
+            let y = (rotation * Vec3::Y * half_size.y).truncate().abs();
+            Rect::from_center_half_size(translation.truncate(), x + y)
+        });
+    let obstacles: Vec<Rect> = doors.chain(fields).collect();
+
+    // Not a change for the systems reading the grid unless the obstacles moved
+    grid.bypass_change_detection().set_obstacles(&obstacles);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An open square with a wall in the middle, leaving a gap at the top
+    fn grid() -> NavGrid {
+        let bounds = Rect::new(0.0, 0.0, 160.0, 160.0);
+        NavGrid::new(bounds, &[Rect::new(76.0, 0.0, 84.0, 120.0)])
+    }
+
+    #[test]
+    fn path_goes_around_walls_and_obstacles() {
+        let mut grid = grid();
+        let (from, to) = (Vec2::new(40.0, 40.0), Vec2::new(120.0, 40.0));
+
+        let path = grid
+            .find_path(from, to)
+            .expect("the gap leads to the other side");
+        assert!(path[0].distance(from) < CELL_SIZE);
+        assert!(path[path.len() - 1].distance(to) < CELL_SIZE);
+        assert!(path.iter().any(|point| point.y > 120.0));
+        assert!(path
+            .iter()
+            .all(|point| !Rect::new(76.0, 0.0, 84.0, 120.0).contains(*point)));
+
+        // A door closing the gap
+        grid.set_obstacles(&[Rect::new(76.0, 120.0, 84.0, 160.0)]);
+        assert_eq!(grid.find_path(from, to), None);
+
+        grid.set_obstacles(&[]);
+        assert!(grid.find_path(from, to).is_some());
+    }
+
+    #[test]
+    fn flees_away_from_the_threat() {
+        let grid = grid();
+        let from = Vec2::new(120.0, 60.0);
+        let threat = Vec2::new(100.0, 60.0);
+        let steps = 4;
+
+        let position = grid.farthest_from(from, threat, steps).unwrap();
+        assert!(position.distance(threat) > from.distance(threat));
+        // Each step moves at most one cell on each axis
+        let reach = (steps as f32 + 1.0) * CELL_SIZE;
+        assert!((position - from).abs().max_element() <= reach);
+    }
+}
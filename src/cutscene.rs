@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+
+use crate::actions::{Action, ActionState};
+use crate::animation::SpriteAnimator;
+use crate::asset_enum::{AssetDictionary, AssetEnumPlugin};
+use crate::camera::effects::CameraEffect;
+use crate::dialog::CurrentDialog;
+use crate::story::StoryFlags;
+use crate::AppState;
+
+use self::asset::{CutsceneAsset, CutsceneLoader, CutsceneStep};
+pub use crate::cutscene::cutscenes::Cutscene;
+
+pub mod asset;
+mod cutscenes;
+
+pub struct CutscenePlugin;
+
+#[derive(Debug, Default, Resource)]
+pub struct CurrentCutscene {
+    cutscene: Cutscene,
+    current_step: usize,
+    started: bool,
+    elapsed: f32,
+    from: Vec3,
+}
+
+impl CurrentCutscene {
+    pub fn set(&mut self, cutscene: Cutscene) {
+        self.cutscene = cutscene;
+        self.current_step = 0;
+        self.started = false;
+    }
+}
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentCutscene>()
+            .add_plugins(AssetEnumPlugin::<Cutscene, CutsceneAsset>::default())
+            .init_asset::<CutsceneAsset>()
+            .init_asset_loader::<CutsceneLoader>()
+            .add_systems(Update, run_cutscene.run_if(in_state(AppState::Cutscene)));
+    }
+}
+
+fn ease(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        1.0
+    } else {
+        elapsed / duration
+    }
+}
+
+type NamedEntities<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Name,
+        &'static mut Transform,
+        Option<&'static mut SpriteAnimator>,
+    ),
+    Without<Camera2d>,
+>;
+
+type NamedEntity<'a> = (
+    &'a Name,
+    Mut<'a, Transform>,
+    Option<Mut<'a, SpriteAnimator>>,
+);
+
+fn find_named<'a>(entities: &'a mut NamedEntities, target: &str) -> Option<NamedEntity<'a>> {
+    entities
+        .iter_mut()
+        .find(|(name, ..)| name.as_str() == target)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_cutscene(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut current_cutscene: ResMut<CurrentCutscene>,
+    mut current_dialog: ResMut<CurrentDialog>,
+    mut story_flags: ResMut<StoryFlags>,
+    mut state: ResMut<NextState<AppState>>,
+    actions: Res<ActionState>,
+    cutscene_dict: Res<AssetDictionary<Cutscene, CutsceneAsset>>,
+    cutscene_assets: Res<Assets<CutsceneAsset>>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    mut entities: NamedEntities,
+    mut camera_effects: EventWriter<CameraEffect>,
+) {
+    let Some(cutscene) = cutscene_dict.get(&current_cutscene.cutscene, &cutscene_assets) else {
+        return;
+    };
+
+    let Some(step) = cutscene.steps.get(current_cutscene.current_step) else {
+        state.set(AppState::InGame);
+        return;
+    };
+
+    let Ok(mut camera_transform) = camera.get_single_mut() else {
+        return;
+    };
+
+    let started = current_cutscene.started;
+    if !started {
+        // Steps acting on an entity that does not exist are skipped
+        if let CutsceneStep::PanTo { target, .. }
+        | CutsceneStep::MoveEntity { target, .. }
+        | CutsceneStep::PlayAnimation { target, .. } = step
+        {
+            if find_named(&mut entities, target).is_none() {
+                warn!("Cutscene target {target} was not found, skipping the step");
+                current_cutscene.current_step += 1;
+                return;
+            }
+        }
+
+        current_cutscene.started = true;
+        current_cutscene.elapsed = 0.0;
+        current_cutscene.from = match step {
+            CutsceneStep::MoveCamera { .. } | CutsceneStep::PanTo { .. } => {
+                camera_transform.translation
+            }
+            CutsceneStep::MoveEntity { target, .. } => find_named(&mut entities, target)
+                .map(|(_, transform, _)| transform.translation)
+                .unwrap_or_default(),
+            _ => Vec3::ZERO,
+        };
+    } else {
+        current_cutscene.elapsed += time.delta_seconds();
+    }
+
+    let elapsed = current_cutscene.elapsed;
+    let from = current_cutscene.from;
+
+    let done = match step {
+        CutsceneStep::Wait(duration) => elapsed >= *duration,
+        CutsceneStep::MoveCamera { to, duration } => {
+            let t = progress(elapsed, *duration);
+            let to = Vec2::new(to.0, to.1);
+            camera_transform.translation = from.lerp(to.extend(from.z), ease(t));
+            t >= 1.0
+        }
+        CutsceneStep::PanTo { target, duration } => {
+            let t = progress(elapsed, *duration);
+            if let Some((_, transform, _)) = find_named(&mut entities, target) {
+                let to = transform.translation.truncate().extend(from.z);
+                camera_transform.translation = from.lerp(to, ease(t));
+            }
+            t >= 1.0
+        }
+        CutsceneStep::MoveEntity {
+            target,
+            to,
+            duration,
+        } => {
+            let t = progress(elapsed, *duration);
+            if let Some((_, mut transform, _)) = find_named(&mut entities, target) {
+                let to = Vec2::new(to.0, to.1).extend(from.z);
+                transform.translation = from.lerp(to, ease(t));
+            }
+            t >= 1.0
+        }
+        CutsceneStep::PlayAnimation { target, animation } => {
+            match find_named(&mut entities, target) {
+                Some((_, _, Some(mut animator))) => {
+                    animator.play(&asset_server.load(animation.clone()));
+                }
+                _ => warn!("Cutscene target {target} has no sprite animator"),
+            }
+            true
+        }
+        CutsceneStep::Dialog(dialog) => {
+            // The cutscene is not running while in dialog, so being here again means the dialog
+            // is over.
+            if !started {
+                current_dialog.set_returning_to(*dialog, AppState::Cutscene);
+                state.set(AppState::InDialog);
+            }
+            started
+        }
+        CutsceneStep::WaitInput => started && actions.just_pressed(Action::Advance),
+        CutsceneStep::Fade { to, duration } => {
+            if !started {
+                camera_effects.send(CameraEffect::Fade {
+                    to: *to,
+                    duration: *duration,
+                });
+            }
+            progress(elapsed, *duration) >= 1.0
+        }
+        CutsceneStep::Effect(effect) => {
+            camera_effects.send(effect.clone());
+            true
+        }
+        CutsceneStep::SetFlag(flag) => {
+            story_flags.set(flag);
+            true
+        }
+    };
+
+    if done {
+        current_cutscene.current_step += 1;
+        current_cutscene.started = false;
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::*;
+
+use crate::camera::effects::CameraEffect;
+use crate::dialog::Dialog;
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum CutsceneStep {
+    Wait(f32),
+    MoveCamera {
+        to: (f32, f32),
+        duration: f32,
+    },
+    PanTo {
+        target: String,
+        duration: f32,
+    },
+    MoveEntity {
+        target: String,
+        to: (f32, f32),
+        duration: f32,
+    },
+    /// Plays an animation on the `SpriteAnimator` of the target, a clip of an atlas layout being
+    /// named like `texture/player.atlas.ron#clip/idle_down`.
+    PlayAnimation {
+        target: String,
+        animation: String,
+    },
+    Dialog(Dialog),
+    WaitInput,
+    Fade {
+        to: f32,
+        duration: f32,
+    },
+    SetFlag(String),
+    /// Starts a camera effect without waiting for it to end.
+    Effect(CameraEffect),
+}
+
+#[derive(Debug, Default, TypePath, Asset)]
+pub struct CutsceneAsset {
+    pub steps: Vec<CutsceneStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CutsceneFile {
+    steps: Vec<CutsceneStep>,
+}
+
+#[derive(Debug, Error)]
+pub enum CutsceneLoadError {
+    #[error("Could not load cutscene: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct CutsceneLoader;
+
+impl AssetLoader for CutsceneLoader {
+    type Asset = CutsceneAsset;
+
+    type Settings = ();
+
+    type Error = CutsceneLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            let cutscene_file: CutsceneFile = ron::de::from_bytes(&buf)?;
+
+            Ok(CutsceneAsset {
+                steps: cutscene_file.steps,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cutscene.ron"]
+    }
+}
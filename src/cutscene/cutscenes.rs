@@ -0,0 +1,5 @@
+use crate::asset_enum::asset_enum_def;
+
+asset_enum_def!(Cutscene, CUTSCENES, [
+    (Intro, "cutscenes/intro.cutscene.ron"),
+]);
@@ -1,16 +1,19 @@
-use bevy::input::gamepad::GamepadButtonChangedEvent;
-use bevy::input::keyboard::KeyboardInput;
-use bevy::input::ButtonState;
 use bevy::prelude::*;
 
+use crate::actions::{Action, ActionState};
 use crate::asset_enum::{AssetDictionary, AssetEnumPlugin};
+use crate::camera::effects::CameraEffect;
+use crate::inventory::Inventory;
+use crate::player::PlayerTag;
 use crate::AppState;
 
 use self::asset::{DialogAsset, DialogLoader};
+use self::bubble::BubblePlugin;
 pub use crate::dialog::dialogs::Dialog;
 pub use crate::dialog::portrait::Portrait;
 
 pub mod asset;
+pub mod bubble;
 mod dialogs;
 mod portrait;
 
@@ -32,12 +35,32 @@ pub struct DialogPortrait;
 pub struct CurrentDialog {
     dialog: Dialog,
     current_line: usize,
+    return_state: AppState,
 }
 
 impl CurrentDialog {
     pub fn set(&mut self, dialog: Dialog) {
+        self.set_returning_to(dialog, AppState::InGame);
+    }
+
+    /// Same as `set` but the game goes to `return_state` instead of `AppState::InGame` once the
+    /// last line has been read.
+    pub fn set_returning_to(&mut self, dialog: Dialog, return_state: AppState) {
         self.dialog = dialog;
         self.current_line = 0;
+        self.return_state = return_state;
+    }
+
+    pub fn dialog(&self) -> Dialog {
+        self.dialog
+    }
+
+    pub fn current_line(&self) -> usize {
+        self.current_line
+    }
+
+    pub fn set_line(&mut self, line: usize) {
+        self.current_line = line;
     }
 }
 
@@ -46,6 +69,7 @@ impl Plugin for DialogPlugin {
         app.init_resource::<CurrentDialog>()
             .add_plugins(AssetEnumPlugin::<Dialog, DialogAsset>::default())
             .add_plugins(AssetEnumPlugin::<Portrait, Image>::default())
+            .add_plugins(BubblePlugin)
             .init_asset::<DialogAsset>()
             .init_asset_loader::<DialogLoader>()
             .add_systems(Startup, setup_dialog)
@@ -54,11 +78,16 @@ impl Plugin for DialogPlugin {
             .add_systems(
                 Update,
                 (
-                    update_dialog_text,
-                    update_dialog_portrait,
-                    (dialog_controller_input, dialog_keyboard_input)
+                    (dialog_input, skip_unavailable_lines)
+                        .chain()
                         .run_if(in_state(AppState::InDialog)),
-                ),
+                    (
+                        update_dialog_text,
+                        update_dialog_portrait,
+                        dialog_line_effects.run_if(in_state(AppState::InDialog)),
+                    ),
+                )
+                    .chain(),
             );
     }
 }
@@ -161,35 +190,6 @@ fn set_hidden<T: Component>(mut dialog_vibility: Query<&mut Visibility, With<T>>
     }
 }
 
-fn dialog_controller_input(
-    mut current_dialog: ResMut<CurrentDialog>,
-    mut events: EventReader<GamepadButtonChangedEvent>,
-    mut state: ResMut<NextState<AppState>>,
-    dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
-    dialog_assets: Res<Assets<DialogAsset>>,
-) {
-    for event in events.read() {
-        match event {
-            GamepadButtonChangedEvent {
-                button_type: GamepadButtonType::East,
-                value,
-                ..
-            } if *value > 0.5 => {
-                current_dialog.current_line += 1;
-                if current_dialog.current_line
-                    >= dialog_dict
-                        .get(&current_dialog.dialog, &dialog_assets)
-                        .map(|dialog| dialog.lines.len())
-                        .unwrap_or(0)
-                {
-                    state.set(AppState::InGame);
-                }
-            }
-            _ => {}
-        }
-    }
-}
-
 fn update_dialog_text(
     dialog: Res<CurrentDialog>,
     mut text_entity: Query<&mut Text, (With<DialogText>, Without<DialogSpeaker>)>,
@@ -210,32 +210,62 @@ fn update_dialog_text(
     }
 }
 
-fn dialog_keyboard_input(
+fn dialog_input(
     mut current_dialog: ResMut<CurrentDialog>,
-    mut events: EventReader<KeyboardInput>,
+    actions: Res<ActionState>,
     mut state: ResMut<NextState<AppState>>,
     dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
     dialog_assets: Res<Assets<DialogAsset>>,
 ) {
-    for event in events.read() {
-        if let KeyboardInput {
-            state: ButtonState::Pressed,
-            key_code: Some(KeyCode::Space),
-            ..
-        } = event
+    if actions.just_pressed(Action::Advance) {
+        current_dialog.current_line += 1;
+        if current_dialog.current_line
+            >= dialog_dict
+                .get(&current_dialog.dialog, &dialog_assets)
+                .map(|dialog| dialog.lines.len())
+                .unwrap_or(0)
         {
-            current_dialog.current_line += 1;
-            if current_dialog.current_line
-                >= dialog_dict
-                    .get(&current_dialog.dialog, &dialog_assets)
-                    .map(|dialog| dialog.lines.len())
-                    .unwrap_or(0)
-            {
-                state.set(AppState::InGame);
-            }
+            state.set(current_dialog.return_state);
+        }
+    }
+}
+
+/// Moves past the lines whose condition the player's items do not meet, ending the dialog if
+/// none is left.
+fn skip_unavailable_lines(
+    mut current_dialog: ResMut<CurrentDialog>,
+    player: Query<&Inventory, With<PlayerTag>>,
+    mut state: ResMut<NextState<AppState>>,
+    dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
+    dialog_assets: Res<Assets<DialogAsset>>,
+) {
+    if !current_dialog.is_changed() {
+        return;
+    }
+    let (Some(dialog), Ok(inventory)) = (
+        dialog_dict.get(&current_dialog.dialog, &dialog_assets),
+        player.get_single(),
+    ) else {
+        return;
+    };
+
+    let mut line = current_dialog.current_line;
+    while dialog.lines.get(line).is_some_and(|line| {
+        line.condition
+            .as_ref()
+            .is_some_and(|condition| !condition.check(inventory))
+    }) {
+        line += 1;
+    }
+
+    if line != current_dialog.current_line {
+        current_dialog.current_line = line;
+        if line >= dialog.lines.len() {
+            state.set(current_dialog.return_state);
         }
     }
 }
+
 fn update_dialog_portrait(
     dialog: Res<CurrentDialog>,
     images: Res<AssetDictionary<Portrait, Image>>,
@@ -252,3 +282,19 @@ fn update_dialog_portrait(
         }
     }
 }
+
+fn dialog_line_effects(
+    dialog: Res<CurrentDialog>,
+    mut camera_effects: EventWriter<CameraEffect>,
+    dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
+    dialog_assets: Res<Assets<DialogAsset>>,
+) {
+    if dialog.is_changed() {
+        if let Some(line) = dialog_dict
+            .get(&dialog.dialog, &dialog_assets)
+            .and_then(|dialog_asset| dialog_asset.lines.get(dialog.current_line))
+        {
+            camera_effects.send_batch(line.effects.iter().cloned());
+        }
+    }
+}
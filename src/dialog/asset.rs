@@ -3,6 +3,9 @@ use bevy::prelude::*;
 use serde::Deserialize;
 use thiserror::*;
 
+use crate::camera::effects::CameraEffect;
+use crate::inventory::ItemCheck;
+
 use super::Portrait;
 
 #[derive(Debug)]
@@ -10,6 +13,10 @@ pub struct DialogLine {
     pub portrait: Portrait,
     pub text: Box<str>,
     pub speaker: Box<str>,
+    /// Camera effects started when the line is shown.
+    pub effects: Vec<CameraEffect>,
+    /// The line is skipped when the player's items do not pass the check.
+    pub condition: Option<ItemCheck>,
 }
 
 #[derive(Debug, Default, TypePath, Asset)]
@@ -20,6 +27,10 @@ pub struct DialogAsset {
 #[derive(Debug, Deserialize)]
 struct DialogFile {
     lines: Vec<(Portrait, String, String)>,
+    #[serde(default)]
+    effects: Vec<(usize, CameraEffect)>,
+    #[serde(default)]
+    conditions: Vec<(usize, ItemCheck)>,
 }
 
 #[derive(Debug, Error)]
@@ -50,16 +61,30 @@ impl AssetLoader for DialogLoader {
             let mut buf = vec![];
             reader.read_to_end(&mut buf).await?;
             let dialog_file: DialogFile = ron::de::from_bytes(&buf)?;
-            let lines = dialog_file
+            let mut lines: Vec<_> = dialog_file
                 .lines
                 .into_iter()
                 .map(|(portrait, speaker, text)| DialogLine {
                     portrait,
                     speaker: speaker.into(),
                     text: text.into(),
+                    effects: vec![],
+                    condition: None,
                 })
                 .collect();
 
+            for (line, effect) in dialog_file.effects {
+                if let Some(line) = lines.get_mut(line) {
+                    line.effects.push(effect);
+                }
+            }
+
+            for (line, condition) in dialog_file.conditions {
+                if let Some(line) = lines.get_mut(line) {
+                    line.condition = Some(condition);
+                }
+            }
+
             let dialog = DialogAsset { lines };
 
             Ok(dialog)
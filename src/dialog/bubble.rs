@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::text::{Text2dBounds, TextLayoutInfo};
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+use bevy_rapier2d::prelude::*;
+
+use crate::asset_enum::AssetDictionary;
+use crate::audio::{Bus, PlaySound, Sound};
+use crate::camera::effects::CameraEffect;
+use crate::inventory::Inventory;
+use crate::player::PlayerTag;
+
+use super::asset::{DialogAsset, DialogLine};
+use super::{Dialog, Portrait};
+
+// Above the darkness of the lightmap
+const BUBBLE_Z: f32 = 6.0;
+const BUBBLE_OFFSET: Vec2 = Vec2::new(0.0, 14.0);
+// The text is rendered bigger and scaled down to stay sharp under the zoom of the camera
+const TEXT_SCALE: f32 = 1.0 / 3.0;
+const TEXT_WIDTH: f32 = 100.0;
+const PORTRAIT_SIZE: f32 = 10.0;
+const PADDING: f32 = 2.0;
+// Seconds a bubble stays on screen, plus some for each character of its text
+const BASE_DURATION: f32 = 1.5;
+const CHARACTER_DURATION: f32 = 0.05;
+// Distance at which the blip of a new bubble can be heard
+const BLIP_RANGE: f32 = 200.0;
+
+pub struct BubblePlugin;
+
+/// Says the lines of a dialog one after the other in bubbles above `speaker`, once it is done
+/// with the lines it was already given. The game keeps running meanwhile.
+#[derive(Debug, Clone, Event)]
+pub struct ShowBubble {
+    pub speaker: Entity,
+    pub dialog: Dialog,
+}
+
+/// The lines a speaker still has to say and the bubble of the one it is saying.
+#[derive(Debug, Default, Component)]
+pub struct BubbleQueue {
+    lines: VecDeque<(Dialog, usize)>,
+    current: Option<Entity>,
+}
+
+#[derive(Debug, Component)]
+pub struct SpeechBubble {
+    speaker: Entity,
+    timer: Timer,
+}
+
+#[derive(Debug, Component)]
+struct BubbleBackground;
+
+#[derive(Debug, Component)]
+struct BubblePortrait;
+
+#[derive(Debug, Component)]
+struct BubbleText;
+
+impl Plugin for BubblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowBubble>()
+            .add_systems(
+                Update,
+                (
+                    (expire_bubbles, queue_bubbles, play_bubbles).chain(),
+                    layout_bubbles,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_speakers
+                    .after(PhysicsSet::Writeback)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn queue_bubbles(
+    mut commands: Commands,
+    mut events: EventReader<ShowBubble>,
+    dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
+    dialog_assets: Res<Assets<DialogAsset>>,
+    mut queues: Query<&mut BubbleQueue>,
+) {
+    let mut new_queues: HashMap<Entity, BubbleQueue> = HashMap::new();
+    for event in events.read() {
+        let Some(dialog) = dialog_dict.get(&event.dialog, &dialog_assets) else {
+            warn!("Bubble dialog {:?} is not loaded", event.dialog);
+            continue;
+        };
+
+        let lines = (0..dialog.lines.len()).map(|line| (event.dialog, line));
+        if let Ok(mut queue) = queues.get_mut(event.speaker) {
+            queue.lines.extend(lines);
+        } else {
+            new_queues
+                .entry(event.speaker)
+                .or_default()
+                .lines
+                .extend(lines);
+        }
+    }
+
+    for (speaker, queue) in new_queues {
+        if let Some(mut speaker) = commands.get_entity(speaker) {
+            speaker.insert(queue);
+        }
+    }
+}
+
+/// Shows the next line of each speaker once its previous bubble expired, skipping the lines
+/// whose condition the player's items do not meet.
+#[allow(clippy::too_many_arguments)]
+fn play_bubbles(
+    mut commands: Commands,
+    mut queues: Query<(Entity, &mut BubbleQueue, &GlobalTransform)>,
+    bubbles: Query<(), With<SpeechBubble>>,
+    player: Query<&Inventory, With<PlayerTag>>,
+    dialog_dict: Res<AssetDictionary<Dialog, DialogAsset>>,
+    dialog_assets: Res<Assets<DialogAsset>>,
+    portraits: Res<AssetDictionary<Portrait, Image>>,
+    mut camera_effects: EventWriter<CameraEffect>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    let inventory = player.get_single().ok();
+
+    for (speaker, mut queue, transform) in queues.iter_mut() {
+        if queue.current.is_some_and(|bubble| bubbles.contains(bubble)) {
+            continue;
+        }
+        queue.current = None;
+
+        while let Some((dialog, line)) = queue.lines.pop_front() {
+            let Some(line) = dialog_dict
+                .get(&dialog, &dialog_assets)
+                .and_then(|dialog| dialog.lines.get(line))
+            else {
+                continue;
+            };
+            let available = line.condition.as_ref().is_none_or(|condition| {
+                inventory.is_some_and(|inventory| condition.check(inventory))
+            });
+            if !available {
+                continue;
+            }
+
+            queue.current = Some(spawn_bubble(&mut commands, speaker, line, &portraits));
+            camera_effects.send_batch(line.effects.iter().cloned());
+            sounds.send(PlaySound {
+                sound: Sound::DialogBlip,
+                bus: Bus::Dialog,
+                position: Some((transform.translation().truncate(), BLIP_RANGE)),
+            });
+            break;
+        }
+    }
+}
+
+fn spawn_bubble(
+    commands: &mut Commands,
+    speaker: Entity,
+    line: &DialogLine,
+    portraits: &AssetDictionary<Portrait, Image>,
+) -> Entity {
+    let duration = BASE_DURATION + CHARACTER_DURATION * line.text.chars().count() as f32;
+    commands
+        .spawn((
+            Name::new("Speech bubble"),
+            SpatialBundle::default(),
+            SpeechBubble {
+                speaker,
+                timer: Timer::from_seconds(duration, TimerMode::Once),
+            },
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.1, 0.1, 0.12, 0.85),
+                        custom_size: Some(Vec2::ZERO),
+                        anchor: Anchor::BottomCenter,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                BubbleBackground,
+            ));
+            commands.spawn((
+                SpriteBundle {
+                    texture: portraits.get_handle(&line.portrait).unwrap_or_default(),
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(PORTRAIT_SIZE)),
+                        anchor: Anchor::BottomLeft,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0.0, PADDING, 0.01),
+                    ..Default::default()
+                },
+                BubblePortrait,
+            ));
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        line.text.to_string(),
+                        TextStyle {
+                            font_size: 24.0,
+                            ..Default::default()
+                        },
+                    ),
+                    text_anchor: Anchor::BottomLeft,
+                    text_2d_bounds: Text2dBounds {
+                        size: Vec2::new(TEXT_WIDTH / TEXT_SCALE, f32::INFINITY),
+                    },
+                    transform: Transform::from_xyz(0.0, PADDING, 0.02)
+                        .with_scale(Vec3::splat(TEXT_SCALE)),
+                    ..Default::default()
+                },
+                BubbleText,
+            ));
+        })
+        .id()
+}
+
+fn expire_bubbles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bubbles: Query<(Entity, &mut SpeechBubble)>,
+) {
+    for (entity, mut bubble) in bubbles.iter_mut() {
+        if bubble.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Sizes the background of the bubbles around their text once it is laid out.
+fn layout_bubbles(
+    texts: Query<(&Parent, Ref<TextLayoutInfo>), With<BubbleText>>,
+    bubbles: Query<&Children, With<SpeechBubble>>,
+    mut backgrounds: Query<&mut Sprite, With<BubbleBackground>>,
+    mut contents: Query<(&mut Transform, Has<BubblePortrait>), Without<BubbleBackground>>,
+) {
+    for (parent, layout) in texts.iter() {
+        if !layout.is_changed() {
+            continue;
+        }
+        let Ok(children) = bubbles.get(parent.get()) else {
+            continue;
+        };
+
+        let text_size = layout.logical_size * TEXT_SCALE;
+        let size = Vec2::new(
+            PORTRAIT_SIZE + text_size.x + PADDING * 3.0,
+            PORTRAIT_SIZE.max(text_size.y) + PADDING * 2.0,
+        );
+        let left = -size.x / 2.0 + PADDING;
+
+        for child in children.iter() {
+            if let Ok(mut sprite) = backgrounds.get_mut(*child) {
+                sprite.custom_size = Some(size);
+            } else if let Ok((mut transform, is_portrait)) = contents.get_mut(*child) {
+                transform.translation.x = if is_portrait {
+                    left
+                } else {
+                    left + PORTRAIT_SIZE + PADDING
+                };
+            }
+        }
+    }
+}
+
+/// Keeps the bubbles above their speaker, removing the ones whose speaker is gone.
+fn follow_speakers(
+    mut commands: Commands,
+    mut bubbles: Query<(Entity, &SpeechBubble, &mut Transform)>,
+    speakers: Query<&Transform, Without<SpeechBubble>>,
+) {
+    for (entity, bubble, mut transform) in bubbles.iter_mut() {
+        let Ok(speaker) = speakers.get(bubble.speaker) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        transform.translation = (speaker.translation.truncate() + BUBBLE_OFFSET).extend(BUBBLE_Z);
+    }
+}
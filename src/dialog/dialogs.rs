@@ -1,7 +1,16 @@
+use serde::{Deserialize, Serialize};
+
 use crate::asset_enum::asset_enum_def;
 
 asset_enum_def!(Dialog, DIALOGS, [
     (Intro, "dialogs/intro.dialog.ron"),
     (Bear, "dialogs/bear.dialog.ron"),
     (Plant, "dialogs/plant.dialog.ron"),
-]);
+    (ThoughtStorage, "dialogs/thoughts/storage.dialog.ron"),
+    (BarkBulkhead, "dialogs/barks/bulkhead.dialog.ron"),
+    (BarkStorageDoor, "dialogs/barks/storage_door.dialog.ron"),
+    (BarkFuseSocket, "dialogs/barks/fuse_socket.dialog.ron"),
+    (WarningSuitHalf, "dialogs/warnings/suit_half.dialog.ron"),
+    (WarningSuitLow, "dialogs/warnings/suit_low.dialog.ron"),
+    (WarningSuitEmpty, "dialogs/warnings/suit_empty.dialog.ron"),
+], derive(Serialize, Deserialize));
@@ -1,13 +1,44 @@
 use bevy::prelude::*;
 use bevy::render::render_resource::{AsBindGroup, ShaderRef};
-use bevy::sprite::Material2d;
+use bevy::sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle};
+use bevy_rapier2d::prelude::*;
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Default)]
+use crate::audio::{Bus, Sound, SoundEmitter};
+use crate::camera::effects::CameraEffect;
+use crate::health::DamageEvent;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::machinery::{spawn_machinery, MachinerySet, PowerNode};
+use crate::player::PlayerTag;
+use crate::story::StoryFlags;
+
+// Seconds taken by the disable animation, fields come back up twice as fast
+const DISABLE_DURATION: f32 = 1.5;
+// Distance at which the hum of the fields can be heard
+const HUM_RANGE: f32 = 160.0;
+// Health lost by the player touching a field
+const CONTACT_DAMAGE: f32 = 20.0;
+
+pub struct ForceFieldPlugin;
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
 pub struct ForceFieldMaterial {
     #[uniform(0)]
     pub color: Color,
     #[uniform(1)]
     pub time: f32,
+    #[uniform(2)]
+    pub strength: f32,
+}
+
+impl Default for ForceFieldMaterial {
+    fn default() -> Self {
+        Self {
+            color: Color::default(),
+            time: 0.0,
+            strength: 1.0,
+        }
+    }
 }
 
 impl Material2d for ForceFieldMaterial {
@@ -16,6 +47,228 @@ impl Material2d for ForceFieldMaterial {
     }
 }
 
+#[derive(Debug, Component)]
+pub struct ForceField {
+    pub enabled: bool,
+    /// Size of the field before its rotation.
+    pub size: Vec2,
+    disabled_by: Option<String>,
+    material: Handle<ForceFieldMaterial>,
+}
+
+impl Plugin for ForceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<ForceFieldMaterial>::default())
+            .add_systems(
+                Update,
+                spawn_forcefields
+                    .after(SpawnLevelSet)
+                    .before(spawn_machinery),
+            )
+            .add_systems(
+                Update,
+                (toggle_forcefields, forcefield_hum, forcefield_contact)
+                    .chain()
+                    .after(SpawnLevelSet)
+                    .after(MachinerySet),
+            )
+            .add_systems(Update, forcefield_update_time);
+    }
+}
+
+/// Spawns the forcefields of the current level each time it is built. They are power nodes and can
+/// be wired to the machinery.
+fn spawn_forcefields(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ForceFieldMaterial>>,
+    story_flags: Res<StoryFlags>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+    if level.forcefields.is_empty() {
+        return;
+    }
+
+    let quad = meshes.add(shape::Quad::new(Vec2::splat(1.0)).into());
+
+    for spec in level.forcefields.iter() {
+        let size = Vec2::new(spec.size.0, spec.size.1);
+        let enabled = !spec
+            .disabled_by
+            .as_ref()
+            .is_some_and(|flag| story_flags.is_set(flag));
+        let material = materials.add(ForceFieldMaterial {
+            color: Color::rgb(spec.color.0, spec.color.1, spec.color.2),
+            strength: if enabled { 1.0 } else { 0.0 },
+            ..Default::default()
+        });
+
+        let mut field = commands.spawn((
+            Name::new(format!("Forcefield {}", spec.name)),
+            SpatialBundle::from_transform(Transform {
+                translation: Vec3::new(spec.position.0, spec.position.1, 0.5),
+                rotation: Quat::from_rotation_z(spec.rotation.to_radians()),
+                ..Default::default()
+            }),
+            RigidBody::Fixed,
+            Collider::cuboid(size.x / 2.0, size.y / 2.0),
+            ActiveEvents::COLLISION_EVENTS,
+            ForceField {
+                enabled,
+                size,
+                disabled_by: spec.disabled_by.clone(),
+                material: material.clone(),
+            },
+            SoundEmitter::new(Sound::ForceFieldHum, Bus::Sfx)
+                .with_range(HUM_RANGE)
+                .with_volume(0.5),
+            PowerNode::new(spec.name.clone()),
+            LevelEntity,
+        ));
+        if !enabled {
+            field.insert(ColliderDisabled);
+        }
+        field.with_children(|commands| {
+            commands.spawn(MaterialMesh2dBundle {
+                mesh: quad.clone().into(),
+                material,
+                transform: Transform::from_scale(size.extend(1.0)),
+                visibility: if enabled {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                },
+                ..Default::default()
+            });
+        });
+    }
+}
+
+/// Follows the power and the story flags of the fields and plays the disable animation.
+fn toggle_forcefields(
+    mut commands: Commands,
+    time: Res<Time>,
+    story_flags: Res<StoryFlags>,
+    mut materials: ResMut<Assets<ForceFieldMaterial>>,
+    mut fields: Query<(Entity, &mut ForceField, &PowerNode, &Children)>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    for (entity, mut field, node, children) in fields.iter_mut() {
+        let enabled = node.powered
+            && !field
+                .disabled_by
+                .as_ref()
+                .is_some_and(|flag| story_flags.is_set(flag));
+        if field.enabled != enabled {
+            field.enabled = enabled;
+            if enabled {
+                commands.entity(entity).remove::<ColliderDisabled>();
+            } else {
+                commands.entity(entity).insert(ColliderDisabled);
+            }
+        }
+
+        let Some(material) = materials.get(&field.material) else {
+            continue;
+        };
+
+        let target = if field.enabled { 1.0 } else { 0.0 };
+        if material.strength == target {
+            continue;
+        }
+
+        let step = time.delta_seconds() / DISABLE_DURATION;
+        let strength = if field.enabled {
+            (material.strength + step * 2.0).min(1.0)
+        } else {
+            (material.strength - step).max(0.0)
+        };
+        materials.get_mut(&field.material).unwrap().strength = strength;
+
+        for child in children.iter() {
+            if let Ok(mut visibility) = visibilities.get_mut(*child) {
+                *visibility = if strength > 0.0 {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
+    }
+}
+
+/// The hum follows the strength of the field and stops once it is disabled.
+fn forcefield_hum(
+    materials: Res<Assets<ForceFieldMaterial>>,
+    mut fields: Query<(&ForceField, &mut SoundEmitter)>,
+) {
+    for (field, mut emitter) in fields.iter_mut() {
+        if let Some(material) = materials.get(&field.material) {
+            emitter.gain = material.strength;
+        }
+    }
+}
+
+/// Touching a field shakes the screen and hurts the player.
+fn forcefield_contact(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut camera_effects: EventWriter<CameraEffect>,
+    mut damage: EventWriter<DamageEvent>,
+    fields: Query<&ForceField>,
+    player: Query<Entity, With<PlayerTag>>,
+    parents: Query<&Parent>,
+    materials: Res<Assets<ForceFieldMaterial>>,
+) {
+    let is_player = |entity: Entity| {
+        player.get_single().is_ok_and(|player| {
+            entity == player
+                || parents
+                    .get(entity)
+                    .is_ok_and(|parent| parent.get() == player)
+        })
+    };
+
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
+            let Some(field) = [entity1, entity2]
+                .into_iter()
+                .find_map(|entity| fields.get(entity).ok())
+            else {
+                continue;
+            };
+            // Creatures bump into the fields too, without the player feeling it
+            if !is_player(entity1) && !is_player(entity2) {
+                continue;
+            }
+
+            let color = materials
+                .get(&field.material)
+                .map_or(Color::WHITE, |material| material.color);
+            camera_effects.send(CameraEffect::Flash {
+                color: (color.r(), color.g(), color.b()),
+                duration: 0.3,
+            });
+            camera_effects.send(CameraEffect::Shake {
+                amplitude: 1.5,
+                frequency: 20.0,
+                decay: 8.0,
+            });
+            damage.send(DamageEvent {
+                amount: CONTACT_DAMAGE,
+            });
+        }
+    }
+}
+
 pub fn forcefield_update_time(time: Res<Time>, mut materials: ResMut<Assets<ForceFieldMaterial>>) {
     for material in materials.iter_mut() {
         material.1.time = time.elapsed_seconds();
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::{Bus, Sound, SoundEmitter};
+use crate::health::DamageEvent;
+use crate::level::asset::{HazardKind, LevelAsset};
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::lighting::{LightAnimation, PointLight2d};
+use crate::machinery::{spawn_machinery, MachinerySet, PowerNode};
+use crate::player::PlayerTag;
+use crate::AppState;
+
+// Distance at which the hazards can be heard
+const HAZARD_SOUND_RANGE: f32 = 120.0;
+
+pub struct HazardPlugin;
+
+/// An area hurting the player inside it while it is active.
+#[derive(Debug, Component)]
+pub struct Hazard {
+    kind: HazardKind,
+    /// Health lost per second inside the area.
+    damage: f32,
+    pub active: bool,
+    intensity: f32,
+}
+
+#[derive(Debug, Component)]
+struct HazardSprite;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            spawn_hazards.after(SpawnLevelSet).before(spawn_machinery),
+        )
+        .add_systems(
+            Update,
+            (
+                update_hazards,
+                hazard_damage.run_if(in_state(AppState::InGame)),
+            )
+                .chain()
+                .after(MachinerySet),
+        );
+    }
+}
+
+/// Spawns the hazards of the current level each time it is built. They are power nodes and can be
+/// wired to the machinery.
+fn spawn_hazards(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+
+    for spec in level.hazards.iter() {
+        let size = Vec2::new(spec.size.0, spec.size.1);
+        let (color, sound, light) = match spec.kind {
+            HazardKind::Arc { .. } => (
+                Color::rgba(0.7, 0.8, 1.0, 0.8),
+                SoundEmitter::new(Sound::ArcCrackle, Bus::Sfx).with_volume(0.6),
+                PointLight2d {
+                    color: Color::rgb(0.6, 0.7, 1.0),
+                    radius: 48.0,
+                    intensity: 0.9,
+                    animation: LightAnimation::Flicker {
+                        speed: 25.0,
+                        amount: 0.6,
+                    },
+                    ..Default::default()
+                },
+            ),
+            HazardKind::Gas => (
+                Color::rgba(0.6, 0.8, 0.5, 0.35),
+                SoundEmitter::new(Sound::ThrusterHiss, Bus::Sfx).with_volume(0.4),
+                PointLight2d {
+                    color: Color::rgb(0.5, 0.8, 0.4),
+                    radius: 32.0,
+                    intensity: 0.3,
+                    animation: LightAnimation::Pulse {
+                        period: 2.0,
+                        amount: 0.5,
+                    },
+                    ..Default::default()
+                },
+            ),
+        };
+
+        commands
+            .spawn((
+                Name::new(spec.name.clone()),
+                SpatialBundle::from_transform(Transform::from_xyz(
+                    spec.position.0,
+                    spec.position.1,
+                    0.3,
+                )),
+                Collider::cuboid(size.x / 2.0, size.y / 2.0),
+                Sensor,
+                Hazard {
+                    kind: spec.kind,
+                    damage: spec.damage,
+                    active: true,
+                    intensity: light.intensity,
+                },
+                sound.with_range(HAZARD_SOUND_RANGE),
+                light,
+                PowerNode::new(spec.name.clone()),
+                LevelEntity,
+            ))
+            .with_children(|commands| {
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(size),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    HazardSprite,
+                ));
+            });
+    }
+}
+
+/// Turns the hazards on and off with their power and the cycle of the arcs.
+fn update_hazards(
+    time: Res<Time>,
+    mut hazards: Query<(
+        &mut Hazard,
+        &PowerNode,
+        &mut SoundEmitter,
+        &mut PointLight2d,
+        &Children,
+    )>,
+    mut sprites: Query<&mut Visibility, With<HazardSprite>>,
+) {
+    for (mut hazard, node, mut sound, mut light, children) in hazards.iter_mut() {
+        let active = node.powered
+            && match hazard.kind {
+                HazardKind::Arc { on, off } => time.elapsed_seconds() % (on + off).max(0.001) < on,
+                HazardKind::Gas => true,
+            };
+        if hazard.active == active {
+            continue;
+        }
+
+        hazard.active = active;
+        sound.gain = if active { 1.0 } else { 0.0 };
+        light.intensity = if active { hazard.intensity } else { 0.0 };
+        for child in children.iter() {
+            if let Ok(mut visibility) = sprites.get_mut(*child) {
+                *visibility = if active {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
+    }
+}
+
+fn hazard_damage(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    hazards: Query<(Entity, &Hazard)>,
+    player: Query<Entity, With<PlayerTag>>,
+    parents: Query<&Parent>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for (entity, hazard) in hazards.iter() {
+        let touching =
+            rapier_context
+                .intersections_with(entity)
+                .any(|(entity1, entity2, intersecting)| {
+                    intersecting && (is_player(entity1) || is_player(entity2))
+                });
+        if hazard.active && touching {
+            damage.send(DamageEvent {
+                amount: hazard.damage * time.delta_seconds(),
+            });
+        }
+    }
+}
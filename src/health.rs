@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::audio::{Bus, PlaySound, Sound};
+use crate::camera::effects::CameraEffect;
+use crate::player::PlayerTag;
+use crate::{AppState, ResetEvent};
+
+// Seconds taken by the screen to fade to black on death, and back once respawned
+const DEATH_FADE: f32 = 1.2;
+// Minimum seconds between two flashes and sounds of the player being hurt
+const HURT_FEEDBACK_INTERVAL: f32 = 0.4;
+
+pub struct HealthPlugin;
+
+#[derive(Debug, Clone, Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+/// Health lost by the player.
+#[derive(Debug, Clone, Event)]
+pub struct DamageEvent {
+    pub amount: f32,
+}
+
+/// The player died and goes back to the last checkpoint once the screen is black.
+#[derive(Debug, Component)]
+pub struct Dying(Timer);
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>().add_systems(
+            Update,
+            (
+                take_damage.run_if(in_state(AppState::InGame)),
+                respawn_after_death,
+                restore_health,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn take_damage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut events: EventReader<DamageEvent>,
+    mut player: Query<(Entity, &mut Health, Has<Dying>), With<PlayerTag>>,
+    mut camera_effects: EventWriter<CameraEffect>,
+    mut sounds: EventWriter<PlaySound>,
+    mut last_feedback: Local<f32>,
+) {
+    let amount: f32 = events.read().map(|event| event.amount).sum();
+    let Ok((player, mut health, dying)) = player.get_single_mut() else {
+        return;
+    };
+    if amount <= 0.0 || dying {
+        return;
+    }
+
+    health.current = (health.current - amount).max(0.0);
+
+    if time.elapsed_seconds() - *last_feedback > HURT_FEEDBACK_INTERVAL {
+        *last_feedback = time.elapsed_seconds();
+        camera_effects.send(CameraEffect::Flash {
+            color: (0.8, 0.0, 0.0),
+            duration: 0.3,
+        });
+        sounds.send(PlaySound::new(Sound::Hurt, Bus::Sfx));
+    }
+
+    if health.current <= 0.0 {
+        info!("The player died");
+        commands
+            .entity(player)
+            .insert(Dying(Timer::from_seconds(DEATH_FADE, TimerMode::Once)));
+        camera_effects.send(CameraEffect::Fade {
+            to: 1.0,
+            duration: DEATH_FADE,
+        });
+    }
+}
+
+/// Goes through the reset path once the screen is black, then fades back in.
+fn respawn_after_death(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dying: Query<(Entity, &mut Dying)>,
+    mut reset_events: EventWriter<ResetEvent>,
+    mut camera_effects: EventWriter<CameraEffect>,
+) {
+    for (entity, mut dying) in dying.iter_mut() {
+        if dying.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Dying>();
+            reset_events.send_default();
+            camera_effects.send(CameraEffect::Fade {
+                to: 0.0,
+                duration: DEATH_FADE,
+            });
+        }
+    }
+}
+
+fn restore_health(mut events: EventReader<ResetEvent>, mut health: Query<&mut Health>) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    for mut health in health.iter_mut() {
+        health.current = health.max;
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, ActionState};
+use crate::audio::{Bus, PlaySound, Sound};
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelSet};
+use crate::lighting::{LightAnimation, PointLight2d};
+use crate::player::PlayerTag;
+use crate::AppState;
+
+use self::asset::{ItemsAsset, ItemsLoader};
+
+pub mod asset;
+
+pub struct InventoryPlugin;
+
+/// Ids of the items carried by the player.
+#[derive(Debug, Default, Clone, PartialEq, Component, Serialize, Deserialize)]
+pub struct Inventory(BTreeSet<String>);
+
+impl Inventory {
+    /// Returns false if the item was already in the inventory.
+    pub fn add(&mut self, item: &str) -> bool {
+        self.0.insert(item.to_string())
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.0.contains(item)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+/// A condition on the items carried by the player.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum ItemCheck {
+    Has(String),
+    Missing(String),
+}
+
+impl ItemCheck {
+    pub fn check(&self, inventory: &Inventory) -> bool {
+        match self {
+            ItemCheck::Has(item) => inventory.contains(item),
+            ItemCheck::Missing(item) => !inventory.contains(item),
+        }
+    }
+}
+
+#[derive(Debug, Resource)]
+pub struct ItemDefinitions(pub Handle<ItemsAsset>);
+
+impl FromWorld for ItemDefinitions {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load("ship.items.ron"))
+    }
+}
+
+/// An item lying in the level, added to the inventory when the player touches it.
+#[derive(Debug, Component)]
+pub struct Pickup(pub String);
+
+#[derive(Debug, Component)]
+struct InventoryPanel;
+
+#[derive(Debug, Component)]
+struct InventoryEntry;
+
+#[derive(Debug, Default, Resource)]
+struct InventoryOpen(bool);
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ItemsAsset>()
+            .init_asset_loader::<ItemsLoader>()
+            .init_resource::<ItemDefinitions>()
+            .init_resource::<InventoryOpen>()
+            .add_systems(Startup, setup_inventory_panel)
+            .add_systems(
+                Update,
+                (
+                    // The player can still drift into a pickup while a dialog stops the game
+                    collect_pickups
+                        .run_if(in_state(AppState::InGame).or_else(in_state(AppState::InDialog))),
+                    spawn_pickups,
+                )
+                    .chain()
+                    .after(SpawnLevelSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_inventory.run_if(in_state(AppState::InGame)),
+                    show_inventory_panel,
+                    update_inventory_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Spawns the pickups of the level whose item the player does not carry, which also brings back
+/// the ones lost by going back to a checkpoint.
+#[allow(clippy::too_many_arguments)]
+fn spawn_pickups(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    definitions: Res<ItemDefinitions>,
+    items: Res<Assets<ItemsAsset>>,
+    player: Query<&Inventory, With<PlayerTag>>,
+    pickups: Query<&Pickup>,
+) {
+    let (Some(level), Some(items), Ok(inventory)) = (
+        levels.get(&level_asset.0),
+        items.get(&definitions.0),
+        player.get_single(),
+    ) else {
+        return;
+    };
+
+    for spec in level.pickups.iter() {
+        if inventory.contains(&spec.item) || pickups.iter().any(|pickup| pickup.0 == spec.item) {
+            continue;
+        }
+
+        let texture = items
+            .get(&spec.item)
+            .map(|item| asset_server.load(item.icon.clone()))
+            .unwrap_or_default();
+        commands.spawn((
+            Name::new(format!("Pickup {}", spec.item)),
+            SpriteBundle {
+                texture,
+                transform: Transform::from_xyz(spec.position.0, spec.position.1, 0.1),
+                ..Default::default()
+            },
+            PointLight2d {
+                color: Color::rgb(0.9, 0.9, 0.7),
+                radius: 24.0,
+                intensity: 0.5,
+                animation: LightAnimation::Pulse {
+                    period: 1.5,
+                    amount: 0.6,
+                },
+                ..Default::default()
+            },
+            Collider::ball(10.0),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            Pickup(spec.item.clone()),
+            LevelEntity,
+        ));
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickups: Query<&Pickup>,
+    mut player: Query<(Entity, &mut Inventory), With<PlayerTag>>,
+    parents: Query<&Parent>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    let Ok((player, mut inventory)) = player.get_single_mut() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
+            let pickup = if is_player(entity2) {
+                entity1
+            } else if is_player(entity1) {
+                entity2
+            } else {
+                continue;
+            };
+
+            if let Ok(Pickup(item)) = pickups.get(pickup) {
+                if inventory.add(item) {
+                    info!("Picked up {item}");
+                    sounds.send(PlaySound::new(Sound::Pickup, Bus::Sfx));
+                }
+                commands.entity(pickup).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn setup_inventory_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                background_color: Color::rgba(0.2, 0.2, 0.2, 0.8).into(),
+                visibility: Visibility::Hidden,
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    right: Val::Px(20.0),
+                    width: Val::Px(400.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            InventoryPanel,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                "Inventory",
+                TextStyle {
+                    font_size: 32.0,
+                    ..Default::default()
+                },
+            ));
+        });
+}
+
+fn toggle_inventory(actions: Res<ActionState>, mut open: ResMut<InventoryOpen>) {
+    if actions.just_pressed(Action::Inventory) {
+        open.0 = !open.0;
+    }
+}
+
+/// The panel is only shown in game, dialogs and menus hide it without closing it.
+fn show_inventory_panel(
+    open: Res<InventoryOpen>,
+    state: Res<State<AppState>>,
+    mut panel: Query<&mut Visibility, With<InventoryPanel>>,
+) {
+    if !open.is_changed() && !state.is_changed() {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if open.0 && *state.get() == AppState::InGame {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Lists the items again each time the inventory or their definitions change.
+#[allow(clippy::too_many_arguments)]
+fn update_inventory_panel(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<ItemsAsset>>,
+    asset_server: Res<AssetServer>,
+    definitions: Res<ItemDefinitions>,
+    items: Res<Assets<ItemsAsset>>,
+    player: Query<Ref<Inventory>, With<PlayerTag>>,
+    panel: Query<Entity, With<InventoryPanel>>,
+    entries: Query<Entity, With<InventoryEntry>>,
+) {
+    let items_changed = asset_events.read().count() > 0;
+    let (Ok(inventory), Ok(panel)) = (player.get_single(), panel.get_single()) else {
+        return;
+    };
+    if !items_changed && !inventory.is_changed() {
+        return;
+    }
+
+    for entity in entries.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let items = items.get(&definitions.0);
+    commands.entity(panel).with_children(|commands| {
+        if inventory.iter().next().is_none() {
+            commands.spawn((
+                TextBundle::from_section(
+                    "Empty",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::GRAY,
+                        ..Default::default()
+                    },
+                ),
+                InventoryEntry,
+            ));
+        }
+
+        for id in inventory.iter() {
+            let item = items.and_then(|items| items.get(id));
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::vertical(Val::Px(5.0)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    InventoryEntry,
+                ))
+                .with_children(|commands| {
+                    commands.spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(48.0),
+                            height: Val::Px(48.0),
+                            margin: UiRect::right(Val::Px(10.0)),
+                            ..Default::default()
+                        },
+                        image: item
+                            .map(|item| asset_server.load(item.icon.clone()))
+                            .unwrap_or_default()
+                            .into(),
+                        ..Default::default()
+                    });
+                    commands
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                flex_shrink: 1.0,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .with_children(|commands| {
+                            commands.spawn(TextBundle::from_section(
+                                item.map_or(id, |item| &item.name),
+                                TextStyle {
+                                    font_size: 24.0,
+                                    ..Default::default()
+                                },
+                            ));
+                            commands.spawn(TextBundle::from_section(
+                                item.map_or("", |item| &item.description),
+                                TextStyle {
+                                    font_size: 18.0,
+                                    color: Color::rgb(0.8, 0.8, 0.8),
+                                    ..Default::default()
+                                },
+                            ));
+                        });
+                });
+        }
+    });
+}
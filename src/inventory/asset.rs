@@ -0,0 +1,60 @@
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::*;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemSpec {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+}
+
+/// Definitions of all the items of the game read from a `.items.ron` file.
+#[derive(Debug, Default, Deserialize, TypePath, Asset)]
+pub struct ItemsAsset {
+    pub items: Vec<ItemSpec>,
+}
+
+impl ItemsAsset {
+    pub fn get(&self, id: &str) -> Option<&ItemSpec> {
+        self.items.iter().find(|item| item.id == id)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ItemsLoadError {
+    #[error("Could not load items: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct ItemsLoader;
+
+impl AssetLoader for ItemsLoader {
+    type Asset = ItemsAsset;
+
+    type Settings = ();
+
+    type Error = ItemsLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            Ok(ron::de::from_bytes(&buf)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["items.ron"]
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::PlayerTag;
+use crate::AppState;
+
+use self::asset::{LevelAsset, LevelLoader};
+
+pub mod asset;
+
+pub struct LevelPlugin;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Level {
+    #[default]
+    Vessel,
+}
+
+impl Level {
+    pub fn asset_path(&self) -> &'static str {
+        match self {
+            Level::Vessel => "levels/vessel.level.ron",
+        }
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct CurrentLevel(pub Level);
+
+/// The data of the current level, loaded again each time it changes.
+#[derive(Debug, Default, Resource)]
+pub struct CurrentLevelAsset(pub Handle<LevelAsset>);
+
+/// Name of the room of the current level the player is in.
+#[derive(Debug, Default, Resource)]
+pub struct CurrentRoom(pub Option<String>);
+
+/// Marks the root entities spawned for the current level so they can be despawned when the level
+/// is rebuilt.
+#[derive(Debug, Component)]
+pub struct LevelEntity;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct SpawnLevelSet;
+
+/// Sent each time the level is rebuilt once its asset is loaded, for the entities it describes to
+/// be spawned.
+#[derive(Debug, Event)]
+pub struct SpawnLevelEvent;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<CurrentLevelAsset>()
+            .init_resource::<CurrentRoom>()
+            .init_asset::<LevelAsset>()
+            .init_asset_loader::<LevelLoader>()
+            .add_event::<SpawnLevelEvent>()
+            .configure_sets(
+                Update,
+                SpawnLevelSet.run_if(resource_changed::<CurrentLevel>()),
+            )
+            .add_systems(Update, reload_level.before(SpawnLevelSet))
+            .add_systems(
+                Update,
+                (
+                    despawn_level,
+                    (load_level_asset, send_spawn_level_event).chain(),
+                )
+                    .in_set(SpawnLevelSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    place_player_at_start.run_if(in_state(AppState::MainMenu)),
+                    update_current_room,
+                )
+                    .after(SpawnLevelSet),
+            );
+    }
+}
+
+fn despawn_level(mut commands: Commands, entities: Query<Entity, With<LevelEntity>>) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn load_level_asset(
+    asset_server: Res<AssetServer>,
+    level: Res<CurrentLevel>,
+    mut level_asset: ResMut<CurrentLevelAsset>,
+) {
+    level_asset.0 = asset_server.load(level.0.asset_path());
+}
+
+/// Moves the player to the start of the level once it is loaded, the game opening on the main
+/// menu before the level is.
+fn place_player_at_start(
+    mut events: EventReader<AssetEvent<LevelAsset>>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    mut player: Query<&mut Transform, With<PlayerTag>>,
+) {
+    if !events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&level_asset.0))
+    {
+        return;
+    }
+
+    if let (Some(level), Ok(mut transform)) = (levels.get(&level_asset.0), player.get_single_mut())
+    {
+        transform.translation = level.start_position().extend(transform.translation.z);
+    }
+}
+
+/// Rebuilds the level once its asset is loaded, and again each time it is modified.
+fn reload_level(
+    mut events: EventReader<AssetEvent<LevelAsset>>,
+    level_asset: Res<CurrentLevelAsset>,
+    mut level: ResMut<CurrentLevel>,
+) {
+    if events.read().any(|event| {
+        event.is_loaded_with_dependencies(&level_asset.0) || event.is_modified(&level_asset.0)
+    }) {
+        level.set_changed();
+    }
+}
+
+fn send_spawn_level_event(
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    mut events: EventWriter<SpawnLevelEvent>,
+) {
+    if levels.contains(&level_asset.0) {
+        events.send(SpawnLevelEvent);
+    }
+}
+
+fn update_current_room(
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    player: Query<&Transform, With<PlayerTag>>,
+    mut current_room: ResMut<CurrentRoom>,
+) {
+    let (Some(level), Ok(player)) = (levels.get(&level_asset.0), player.get_single()) else {
+        return;
+    };
+
+    let room = level
+        .rooms
+        .iter()
+        .find(|room| room.contains(player.translation.truncate()))
+        .map(|room| &room.name);
+    if current_room.0.as_ref() != room {
+        info!("Entering room {room:?}");
+        current_room.0 = room.cloned();
+    }
+}
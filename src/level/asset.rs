@@ -0,0 +1,273 @@
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::*;
+
+use crate::audio::Sound;
+use crate::dialog::Dialog;
+use crate::inventory::ItemCheck;
+
+/// A background layer scrolling slower than the level to give an illusion of depth.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParallaxLayerSpec {
+    pub texture: String,
+    /// Fraction of the camera movement followed by the layer, 0.0 stays fixed with the level
+    /// and 1.0 is infinitely far away.
+    pub factor: f32,
+    /// Position of the texture origin when the camera is at the origin of the level.
+    #[serde(default)]
+    pub offset: (f32, f32),
+    /// Size of a texture pixel in world units.
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    pub z: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// A named area of the level with its own atmosphere.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomSpec {
+    pub name: String,
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+    /// Opacity of the darkness where no light reaches, from 0.0 to 1.0.
+    #[serde(default)]
+    pub darkness: f32,
+    /// Music played while the player is in the room instead of the one of the level.
+    #[serde(default)]
+    pub music: Option<Sound>,
+}
+
+impl RoomSpec {
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.min.0, self.min.1, self.max.0, self.max.1)
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        self.rect().contains(position)
+    }
+}
+
+/// A spot recording the state of the world when the player reaches it, to respawn there.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckpointSpec {
+    pub name: String,
+    pub position: (f32, f32),
+}
+
+/// An item lying in the level until the player picks it up.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PickupSpec {
+    /// Id of the item in the items definitions.
+    pub item: String,
+    pub position: (f32, f32),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SwitchKind {
+    #[default]
+    Switch,
+    /// A switch with a handle swinging from one side to the other.
+    Lever,
+}
+
+/// A switch toggling a story flag, it lets the power through while the flag is set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SwitchSpec {
+    pub name: String,
+    pub position: (f32, f32),
+    pub flag: String,
+    #[serde(default)]
+    pub kind: SwitchKind,
+}
+
+/// A junction of the power wires, producing power when it is a source.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PowerNodeSpec {
+    pub name: String,
+    pub position: (f32, f32),
+    #[serde(default)]
+    pub source: bool,
+    /// The node only lets the power through while the player's items pass the check.
+    #[serde(default)]
+    pub requires: Option<ItemCheck>,
+}
+
+/// A door sliding along its length, open while it is powered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DoorSpec {
+    pub name: String,
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    /// The door stays closed unless the player's items pass the check.
+    #[serde(default)]
+    pub requires: Option<ItemCheck>,
+}
+
+/// A light only shining while it is powered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoweredLightSpec {
+    pub name: String,
+    pub position: (f32, f32),
+    pub color: (f32, f32, f32),
+    pub radius: f32,
+    pub intensity: f32,
+}
+
+/// The machinery of a level and the wires carrying the power between them by name. Forcefields
+/// can be wired with their name too.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct MachinerySpec {
+    pub switches: Vec<SwitchSpec>,
+    pub nodes: Vec<PowerNodeSpec>,
+    pub doors: Vec<DoorSpec>,
+    pub lights: Vec<PoweredLightSpec>,
+    pub wires: Vec<(String, String)>,
+}
+
+/// A wall of energy blocking the way while it is powered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForceFieldSpec {
+    pub name: String,
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    /// Angle in degrees.
+    #[serde(default)]
+    pub rotation: f32,
+    pub color: (f32, f32, f32),
+    /// Story flag turning the field off while it is set.
+    #[serde(default)]
+    pub disabled_by: Option<String>,
+}
+
+/// A spot the companion comments on the first time the player comes near it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PointOfInterestSpec {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub bark: Dialog,
+    /// The companion keeps quiet unless the player's items pass the check.
+    #[serde(default)]
+    pub requires: Option<ItemCheck>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum HazardKind {
+    /// Arcs for `on` seconds every `on + off` seconds.
+    Arc { on: f32, off: f32 },
+    /// Vents gas all the time.
+    Gas,
+}
+
+/// An area hurting the player inside it while it is powered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HazardSpec {
+    pub name: String,
+    pub kind: HazardKind,
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    /// Health lost per second inside the area.
+    pub damage: f32,
+}
+
+/// A station refilling the suit of the player standing at it while it is powered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RechargeStationSpec {
+    pub name: String,
+    pub position: (f32, f32),
+}
+
+/// A creature living in the level.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatureSpec {
+    pub name: String,
+    /// Path of the `.creature.ron` file defining it.
+    pub creature: String,
+    pub position: (f32, f32),
+    /// Points it goes through in turn while patrolling, it stays home without any.
+    #[serde(default)]
+    pub patrol: Vec<(f32, f32)>,
+}
+
+/// Data of a level read from a `.level.ron` file.
+#[derive(Debug, Default, Deserialize, TypePath, Asset)]
+pub struct LevelAsset {
+    /// Where the player starts the level.
+    pub start: (f32, f32),
+    #[serde(default)]
+    pub parallax: Vec<ParallaxLayerSpec>,
+    #[serde(default)]
+    pub rooms: Vec<RoomSpec>,
+    /// Darkness outside of any room.
+    #[serde(default)]
+    pub darkness: f32,
+    #[serde(default)]
+    pub music: Option<Sound>,
+    /// Checkpoints besides the start of the level.
+    #[serde(default)]
+    pub checkpoints: Vec<CheckpointSpec>,
+    #[serde(default)]
+    pub pickups: Vec<PickupSpec>,
+    #[serde(default)]
+    pub machinery: MachinerySpec,
+    #[serde(default)]
+    pub forcefields: Vec<ForceFieldSpec>,
+    #[serde(default)]
+    pub points_of_interest: Vec<PointOfInterestSpec>,
+    #[serde(default)]
+    pub hazards: Vec<HazardSpec>,
+    #[serde(default)]
+    pub recharge_stations: Vec<RechargeStationSpec>,
+    #[serde(default)]
+    pub creatures: Vec<CreatureSpec>,
+}
+
+impl LevelAsset {
+    pub fn start_position(&self) -> Vec2 {
+        Vec2::new(self.start.0, self.start.1)
+    }
+
+    pub fn room(&self, name: &str) -> Option<&RoomSpec> {
+        self.rooms.iter().find(|room| room.name == name)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LevelLoadError {
+    #[error("Could not load level: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = LevelAsset;
+
+    type Settings = ();
+
+    type Error = LevelLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            Ok(ron::de::from_bytes(&buf)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
@@ -0,0 +1,559 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::transform::TransformSystem;
+
+use crate::camera::CameraSet;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, CurrentRoom};
+use crate::player::animation::PlayerAnimation;
+use crate::player::PlayerTag;
+use crate::tilemap::{map_bounds, wall_rects};
+
+use self::visibility::{is_visible, visibility_polygon};
+
+pub mod visibility;
+
+// Resolution of the lightmap, stretched over the whole view
+const LIGHTMAP_SIZE: UVec2 = UVec2::new(160, 90);
+// Above the level and the player, below the camera
+const LIGHTMAP_Z: f32 = 5.0;
+// Speed at which the darkness changes when entering another room
+const DARKNESS_BLEND_SPEED: f32 = 2.0;
+// Extra size of the lightmap around the view, hides the edges while the camera moves
+const VIEW_MARGIN: f32 = 1.1;
+// Opacity of the parts of the rooms the player cannot see
+const HIDDEN_DARKNESS: f32 = 0.97;
+
+pub struct LightingPlugin;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LightAnimation {
+    #[default]
+    Steady,
+    /// Randomly dims the light by up to `amount` of its intensity.
+    Flicker { speed: f32, amount: f32 },
+    /// Dims the light by `amount` of its intensity and back every `period` seconds.
+    Pulse { period: f32, amount: f32 },
+}
+
+impl LightAnimation {
+    fn factor(&self, time: f32, phase: f32) -> f32 {
+        match *self {
+            LightAnimation::Steady => 1.0,
+            LightAnimation::Flicker { speed, amount } => {
+                let t = time * speed + phase;
+                let noise = (t * 1.7).sin() * (t * 3.1 + phase).sin() * (t * 0.7).cos();
+                1.0 - amount * noise.abs()
+            }
+            LightAnimation::Pulse { period, amount } => {
+                let t = time / period.max(0.001) + phase;
+                1.0 - amount * (0.5 - 0.5 * (t * TAU).cos())
+            }
+        }
+    }
+}
+
+/// A light shining around its entity, fading out at `radius` pixels.
+#[derive(Debug, Clone, Component)]
+pub struct PointLight2d {
+    pub color: Color,
+    pub radius: f32,
+    pub intensity: f32,
+    pub animation: LightAnimation,
+    /// Offsets the animation so identical lights do not change in sync.
+    pub phase: f32,
+}
+
+impl Default for PointLight2d {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            radius: 64.0,
+            intensity: 1.0,
+            animation: LightAnimation::Steady,
+            phase: 0.0,
+        }
+    }
+}
+
+/// A cone of light pointing where the player is facing.
+#[derive(Debug, Clone, Component)]
+pub struct Flashlight {
+    pub color: Color,
+    pub range: f32,
+    /// Half of the opening angle of the cone, in radians.
+    pub half_angle: f32,
+    pub intensity: f32,
+    pub on: bool,
+}
+
+impl Default for Flashlight {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.95, 0.8),
+            range: 120.0,
+            half_angle: 0.45,
+            intensity: 1.0,
+            on: true,
+        }
+    }
+}
+
+/// The area lit by a light, computed again each time the light moves. The visibility polygon
+/// of the player is also its line of sight.
+#[derive(Debug, Default, Component)]
+pub struct LightShadow {
+    origin: Option<Vec2>,
+    pub polygon: Vec<Vec2>,
+    mask: ShadowMask,
+}
+
+/// The texels inside of a visibility polygon. The texels are aligned with the world rather than
+/// the view, so the mask stays valid while the camera moves.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ShadowMask {
+    texel: Vec2,
+    /// Position of the first texel of the mask, in texels.
+    min: IVec2,
+    size: UVec2,
+    lit: Vec<bool>,
+}
+
+impl ShadowMask {
+    /// Rasterizes the `polygon` seen from `origin` over the texels of `area`.
+    pub fn new(polygon: &[Vec2], walls: &[Rect], origin: Vec2, area: Rect, texel: Vec2) -> Self {
+        let min = (area.min / texel).floor().as_ivec2();
+        let size = ((area.max / texel).ceil().as_ivec2() - min)
+            .max(IVec2::ZERO)
+            .as_uvec2();
+        let lit = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| IVec2::new(x as i32, y as i32)))
+            .map(|cell| {
+                let point = ((min + cell).as_vec2() + 0.5) * texel;
+                is_visible(polygon, walls, origin, point)
+            })
+            .collect();
+        Self {
+            texel,
+            min,
+            size,
+            lit,
+        }
+    }
+
+    /// Whether the texel containing `point` is inside of the polygon, the texels outside of the
+    /// mask are not.
+    pub fn is_lit(&self, point: Vec2) -> bool {
+        let cell = (point / self.texel).floor().as_ivec2() - self.min;
+        cell.cmpge(IVec2::ZERO).all()
+            && cell.cmplt(self.size.as_ivec2()).all()
+            && self.lit[(cell.y as u32 * self.size.x + cell.x as u32) as usize]
+    }
+}
+
+#[derive(Debug, Component)]
+struct Lightmap;
+
+#[derive(Debug, Default, Resource)]
+struct AmbientDarkness(f32);
+
+/// The static geometry blocking light, the same as the colliders of the walls.
+#[derive(Debug, Resource)]
+struct Occluders {
+    walls: Vec<Rect>,
+    bounds: Rect,
+}
+
+impl Default for Occluders {
+    fn default() -> Self {
+        Self {
+            walls: wall_rects(),
+            bounds: map_bounds(),
+        }
+    }
+}
+
+/// A light once its animation and transform have been applied.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample<'a> {
+    pub position: Vec2,
+    pub color: Vec3,
+    pub radius: f32,
+    pub intensity: f32,
+    /// Direction and cosine of the half angle for cone lights.
+    pub cone: Option<(Vec2, f32)>,
+    /// Texels lit by the light, everything else is in the shadow.
+    pub shadow: Option<&'a ShadowMask>,
+}
+
+/// What the player can see, the parts of the `rooms` outside of `mask` being hidden.
+#[derive(Debug, Clone, Copy)]
+pub struct LineOfSight<'a> {
+    pub mask: &'a ShadowMask,
+    pub rooms: &'a [Rect],
+}
+
+impl LightSample<'_> {
+    fn illuminance(&self, point: Vec2) -> f32 {
+        let offset = point - self.position;
+        let distance = offset.length();
+        if distance >= self.radius {
+            return 0.0;
+        }
+        if let Some(shadow) = self.shadow {
+            if !shadow.is_lit(point) {
+                return 0.0;
+            }
+        }
+
+        let mut light = 1.0 - distance / self.radius;
+        light *= light;
+
+        if let Some((direction, cos_half_angle)) = self.cone {
+            if distance > 0.0 {
+                let cos = offset.dot(direction) / distance;
+                // Soft edge on the last tenth of the cone
+                let edge = (1.0 - cos_half_angle) * 0.1;
+                light *= ((cos - cos_half_angle) / edge.max(0.0001)).clamp(0.0, 1.0);
+            }
+        }
+
+        light * self.intensity
+    }
+}
+
+/// Writes the RGBA8 pixels of a darkness overlay covering `view` with the given `lights` into
+/// `data`. Runs entirely on the CPU so it works without a GPU.
+pub fn compute_lightmap(
+    data: &mut [u8],
+    view: Rect,
+    size: UVec2,
+    darkness: f32,
+    lights: &[LightSample],
+    line_of_sight: Option<LineOfSight>,
+) {
+    let texel = view.size() / size.as_vec2();
+    for (index, pixel) in data.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (index as u32 % size.x, index as u32 / size.x);
+        // Images go down while the world goes up
+        let point = Vec2::new(
+            view.min.x + (x as f32 + 0.5) * texel.x,
+            view.max.y - (y as f32 + 0.5) * texel.y,
+        );
+
+        let mut light = 0.0;
+        let mut color = Vec3::ZERO;
+        for sample in lights {
+            let illuminance = sample.illuminance(point);
+            light += illuminance;
+            color += sample.color * illuminance;
+        }
+
+        let hidden = line_of_sight.is_some_and(|line_of_sight| {
+            line_of_sight.rooms.iter().any(|room| room.contains(point))
+                && !line_of_sight.mask.is_lit(point)
+        });
+
+        let light = if hidden { 0.0 } else { light.min(1.0) };
+        let tint = if light > 0.0 {
+            color / color.max_element().max(1.0) * 0.25
+        } else {
+            Vec3::ZERO
+        };
+        let alpha = if hidden {
+            HIDDEN_DARKNESS.max(darkness)
+        } else {
+            darkness * (1.0 - light)
+        };
+        pixel.copy_from_slice(&[
+            (tint.x * 255.0) as u8,
+            (tint.y * 255.0) as u8,
+            (tint.z * 255.0) as u8,
+            (alpha * 255.0) as u8,
+        ]);
+    }
+}
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AmbientDarkness>()
+            .init_resource::<Occluders>()
+            .add_systems(Startup, setup_lightmap)
+            .add_systems(Update, (update_darkness, add_shadows))
+            .add_systems(
+                PostUpdate,
+                (update_shadows, update_lightmap)
+                    .chain()
+                    .after(CameraSet)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn setup_lightmap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: LIGHTMAP_SIZE.x,
+            height: LIGHTMAP_SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::linear();
+
+    commands.spawn((
+        Name::new("Lightmap"),
+        SpriteBundle {
+            texture: images.add(image),
+            transform: Transform::from_xyz(0.0, 0.0, LIGHTMAP_Z),
+            ..Default::default()
+        },
+        Lightmap,
+    ));
+}
+
+fn update_darkness(
+    time: Res<Time>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    current_room: Res<CurrentRoom>,
+    mut darkness: ResMut<AmbientDarkness>,
+) {
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+
+    let target = current_room
+        .0
+        .as_ref()
+        .and_then(|room| level.room(room))
+        .map_or(level.darkness, |room| room.darkness);
+    let t = (DARKNESS_BLEND_SPEED * time.delta_seconds()).min(1.0);
+    darkness.0 += (target - darkness.0) * t;
+}
+
+type UnshadowedLights<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    (
+        Or<(With<PointLight2d>, With<Flashlight>)>,
+        Without<LightShadow>,
+    ),
+>;
+
+fn add_shadows(mut commands: Commands, lights: UnshadowedLights) {
+    for entity in lights.iter() {
+        commands.entity(entity).insert(LightShadow::default());
+    }
+}
+
+/// Size of a texel of the lightmap in the world, the lightmap being stretched over the view.
+fn lightmap_texel(projection: &OrthographicProjection) -> Vec2 {
+    projection.area.size() * VIEW_MARGIN / LIGHTMAP_SIZE.as_vec2()
+}
+
+type ShadowQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GlobalTransform,
+        &'static mut LightShadow,
+        Option<&'static PointLight2d>,
+        Option<&'static Flashlight>,
+    ),
+>;
+
+fn update_shadows(
+    occluders: Res<Occluders>,
+    camera: Query<&OrthographicProjection, With<Camera2d>>,
+    mut shadows: ShadowQuery,
+) {
+    let Ok(projection) = camera.get_single() else {
+        return;
+    };
+    let texel = lightmap_texel(projection);
+
+    for (transform, mut shadow, light, flashlight) in shadows.iter_mut() {
+        let origin = transform.translation().truncate();
+        let moved = shadow.origin != Some(origin);
+        if moved {
+            shadow.polygon = visibility_polygon(origin, &occluders.walls, occluders.bounds);
+            shadow.origin = Some(origin);
+        }
+        // Rasterized again only when the light moves or the camera zooms
+        if moved || shadow.mask.texel != texel {
+            let reach = light
+                .map_or(0.0, |light| light.radius)
+                .max(flashlight.map_or(0.0, |flashlight| flashlight.range));
+            let area = Rect::from_center_size(origin, Vec2::splat(reach * 2.0));
+            shadow.mask = ShadowMask::new(&shadow.polygon, &occluders.walls, origin, area, texel);
+        }
+    }
+}
+
+/// The line of sight of the player over the rooms in view, kept until the player or the view
+/// moves.
+#[derive(Debug, Default)]
+struct SightCache {
+    origin: Option<Vec2>,
+    area: Rect,
+    mask: ShadowMask,
+}
+
+type LightmapQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut Sprite,
+        &'static Handle<Image>,
+    ),
+    (With<Lightmap>, Without<Camera2d>),
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn update_lightmap(
+    time: Res<Time>,
+    darkness: Res<AmbientDarkness>,
+    occluders: Res<Occluders>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    mut images: ResMut<Assets<Image>>,
+    mut sight: Local<SightCache>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut lightmap: LightmapQuery,
+    lights: Query<(&GlobalTransform, &PointLight2d, Option<&LightShadow>)>,
+    flashlights: Query<(
+        &GlobalTransform,
+        &Flashlight,
+        Option<&PlayerAnimation>,
+        Option<&LightShadow>,
+    )>,
+    player: Query<&LightShadow, With<PlayerTag>>,
+) {
+    let Ok((camera, projection)) = camera.get_single() else {
+        return;
+    };
+    let Ok((mut transform, mut sprite, handle)) = lightmap.get_single_mut() else {
+        return;
+    };
+
+    // Snapped to the texels of the shadow masks
+    let texel = lightmap_texel(projection);
+    let corner = camera.translation.truncate() - projection.area.size() * VIEW_MARGIN / 2.0;
+    let min = (corner / texel).floor() * texel;
+    let view = Rect::from_corners(min, min + texel * LIGHTMAP_SIZE.as_vec2());
+    transform.translation.x = view.center().x;
+    transform.translation.y = view.center().y;
+    sprite.custom_size = Some(view.size());
+
+    let elapsed = time.elapsed_seconds();
+    let samples: Vec<_> = lights
+        .iter()
+        .map(|(transform, light, shadow)| LightSample {
+            position: transform.translation().truncate(),
+            color: Vec3::from_slice(&light.color.as_rgba_f32()),
+            radius: light.radius,
+            intensity: light.intensity * light.animation.factor(elapsed, light.phase),
+            cone: None,
+            shadow: shadow.map(|shadow| &shadow.mask),
+        })
+        .chain(
+            flashlights
+                .iter()
+                .filter(|(_, flashlight, _, _)| flashlight.on)
+                .map(|(transform, flashlight, animation, shadow)| LightSample {
+                    position: transform.translation().truncate(),
+                    color: Vec3::from_slice(&flashlight.color.as_rgba_f32()),
+                    radius: flashlight.range,
+                    intensity: flashlight.intensity,
+                    cone: Some((
+                        animation.map_or(Vec2::NEG_Y, |animation| animation.facing.direction()),
+                        flashlight.half_angle.cos(),
+                    )),
+                    shadow: shadow.map(|shadow| &shadow.mask),
+                }),
+        )
+        .filter(|sample| {
+            let reach = Vec2::splat(sample.radius);
+            Rect::from_corners(view.min - reach, view.max + reach).contains(sample.position)
+        })
+        .collect();
+
+    let rooms: Vec<_> = levels
+        .get(&level_asset.0)
+        .map(|level| level.rooms.iter().map(|room| room.rect()).collect())
+        .unwrap_or_default();
+    // Nothing is hidden until the line of sight of the player has been computed
+    let seen = player
+        .get_single()
+        .ok()
+        .and_then(|shadow| Some((shadow.origin?, &shadow.polygon)));
+    let hull = rooms.iter().copied().reduce(|hull, room| hull.union(room));
+    let line_of_sight = if let (Some((origin, polygon)), Some(hull)) = (seen, hull) {
+        let area = hull.intersect(view);
+        if sight.origin != Some(origin) || sight.area != area {
+            sight.mask = ShadowMask::new(polygon, &occluders.walls, origin, area, texel);
+            sight.origin = Some(origin);
+            sight.area = area;
+        }
+        Some(LineOfSight {
+            mask: &sight.mask,
+            rooms: &rooms,
+        })
+    } else {
+        None
+    };
+
+    if let Some(image) = images.get_mut(handle) {
+        compute_lightmap(
+            &mut image.data,
+            view,
+            LIGHTMAP_SIZE,
+            darkness.0,
+            &samples,
+            line_of_sight,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_casts_a_shadow_on_the_lightmap() {
+        let size = UVec2::new(10, 10);
+        let view = Rect::new(-50.0, -50.0, 50.0, 50.0);
+        let walls = [Rect::new(20.0, -50.0, 30.0, 50.0)];
+        let area = Rect::new(-100.0, -100.0, 100.0, 100.0);
+        let polygon = visibility_polygon(Vec2::ZERO, &walls, area);
+        let texel = view.size() / size.as_vec2();
+        let mask = ShadowMask::new(&polygon, &walls, Vec2::ZERO, area, texel);
+        let light = LightSample {
+            position: Vec2::ZERO,
+            color: Vec3::ONE,
+            radius: 100.0,
+            intensity: 1.0,
+            cone: None,
+            shadow: Some(&mask),
+        };
+
+        let darkness = 0.8;
+        let mut data = vec![0; (size.x * size.y * 4) as usize];
+        compute_lightmap(&mut data, view, size, darkness, &[light], None);
+        let alpha = |x: u32, y: u32| data[((y * size.x + x) * 4 + 3) as usize];
+
+        // Texels are 10 pixels wide, the fifth row is centered 5 pixels above the light
+        let behind_wall = alpha(9, 4);
+        let in_front_of_wall = alpha(1, 4);
+        assert_eq!(behind_wall, (darkness * 255.0) as u8);
+        assert!(in_front_of_wall < behind_wall);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+// Angle on each side of a corner at which extra rays are cast to see past it
+const CORNER_EPSILON: f32 = 0.0001;
+// Distance by which points are moved out of a wall when looking at its visible side
+const WALL_NUDGE: f32 = 0.5;
+
+fn edges(rect: &Rect) -> [(Vec2, Vec2); 4] {
+    let (min, max) = (rect.min, rect.max);
+    let (top_left, bottom_right) = (Vec2::new(min.x, max.y), Vec2::new(max.x, min.y));
+    [
+        (min, top_left),
+        (top_left, max),
+        (max, bottom_right),
+        (bottom_right, min),
+    ]
+}
+
+/// Distance along `direction` at which the ray from `origin` hits the segment, if it does.
+fn ray_hit(origin: Vec2, direction: Vec2, (start, end): (Vec2, Vec2)) -> Option<f32> {
+    let segment = end - start;
+    let denominator = direction.perp_dot(segment);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let offset = start - origin;
+    let distance = offset.perp_dot(segment) / denominator;
+    let along = offset.perp_dot(direction) / denominator;
+    (distance >= 0.0 && (0.0..=1.0).contains(&along)).then_some(distance)
+}
+
+/// Computes the area visible from `origin`, `walls` blocking the view and `bounds` limiting it.
+/// Walls containing `origin` are ignored so lights mounted on them still shine.
+///
+/// Returns the vertices of the polygon sorted by angle around `origin`.
+pub fn visibility_polygon(origin: Vec2, walls: &[Rect], bounds: Rect) -> Vec<Vec2> {
+    let segments: Vec<_> = walls
+        .iter()
+        .filter(|wall| !wall.contains(origin))
+        .chain(std::iter::once(&bounds))
+        .flat_map(edges)
+        .collect();
+
+    // The polygon only changes direction at the corners, one ray on each side of them is
+    // enough to see what is behind
+    let mut angles: Vec<f32> = segments
+        .iter()
+        .flat_map(|&(start, end)| [start, end])
+        .flat_map(|corner| {
+            let offset = corner - origin;
+            let angle = offset.y.atan2(offset.x);
+            [angle - CORNER_EPSILON, angle, angle + CORNER_EPSILON]
+        })
+        .collect();
+    angles.sort_by(f32::total_cmp);
+    angles.dedup();
+
+    angles
+        .into_iter()
+        .filter_map(|angle| {
+            let direction = Vec2::from_angle(angle);
+            segments
+                .iter()
+                .filter_map(|&segment| ray_hit(origin, direction, segment))
+                .min_by(f32::total_cmp)
+                .map(|distance| origin + direction * distance)
+        })
+        .collect()
+}
+
+/// Whether `point` is inside `polygon`, using the even-odd rule.
+pub fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() {
+        Some(&previous) => previous,
+        None => return false,
+    };
+    for &vertex in polygon {
+        if (vertex.y > point.y) != (previous.y > point.y) {
+            let x =
+                vertex.x + (point.y - vertex.y) / (previous.y - vertex.y) * (previous.x - vertex.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+        previous = vertex;
+    }
+    inside
+}
+
+/// Whether `point` can be seen from `origin` given the `polygon` visible from there. Points
+/// inside a wall are visible when the side of the wall facing `origin` is, so walls get lit
+/// instead of casting a shadow on themselves.
+pub fn is_visible(polygon: &[Vec2], walls: &[Rect], origin: Vec2, point: Vec2) -> bool {
+    let mut point = point;
+    // Walls can be side by side, walks back through all of them
+    for _ in 0..walls.len() {
+        let Some(wall) = walls
+            .iter()
+            .find(|wall| wall.contains(point) && !wall.contains(origin))
+        else {
+            break;
+        };
+
+        let direction = point - origin;
+        let Some(distance) = edges(wall)
+            .into_iter()
+            .filter_map(|edge| ray_hit(origin, direction, edge))
+            .min_by(f32::total_cmp)
+        else {
+            break;
+        };
+        point = origin + direction * distance - direction.normalize_or_zero() * WALL_NUDGE;
+    }
+    polygon_contains(polygon, point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::asset::LevelAsset;
+    use crate::tilemap::{map_bounds, wall_rects};
+
+    #[test]
+    fn cabin_is_enclosed_by_the_map_walls() {
+        let walls = wall_rects();
+        let level: LevelAsset =
+            ron::from_str(include_str!("../../assets/levels/vessel.level.ron")).unwrap();
+        let origin = level.start_position();
+        let polygon = visibility_polygon(origin, &walls, map_bounds());
+        assert!(!polygon.is_empty());
+
+        // The walls surround the start position, the view never reaches outside of them
+        let hull = walls.iter().fold(walls[0], |hull, wall| hull.union(*wall));
+        for vertex in polygon.iter() {
+            assert!(
+                hull.contains(*vertex),
+                "{vertex} is outside of the walls {hull:?}"
+            );
+        }
+
+        assert!(polygon_contains(&polygon, origin));
+        assert!(is_visible(
+            &polygon,
+            &walls,
+            origin,
+            origin + Vec2::X * 100.0
+        ));
+        let bounds = map_bounds();
+        assert!(!is_visible(&polygon, &walls, origin, bounds.min + 1.0));
+        assert!(!is_visible(&polygon, &walls, origin, bounds.max - 1.0));
+    }
+}
@@ -0,0 +1,528 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::{HashMap, HashSet};
+use bevy_rapier2d::prelude::*;
+
+use crate::actions::{Action, ActionState};
+use crate::audio::{Bus, PlaySound, Sound};
+use crate::inventory::{Inventory, ItemCheck};
+use crate::level::asset::{LevelAsset, SwitchKind};
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::lighting::PointLight2d;
+use crate::player::PlayerTag;
+use crate::story::StoryFlags;
+use crate::AppState;
+
+// Seconds taken by a door to open or close
+const DOOR_DURATION: f32 = 0.8;
+// Distance at which the doors can be heard
+const DOOR_SOUND_RANGE: f32 = 240.0;
+// Angle of the lever handles on each side, in radians
+const LEVER_ANGLE: f32 = 0.6;
+const LEVER_SPEED: f32 = 6.0;
+
+pub struct MachineryPlugin;
+
+/// Runs the power graph and the machinery, before the forcefields follow their power.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct MachinerySet;
+
+/// A machine connected to the power wires by its name. Machines left out of the wiring run on
+/// the ship's own power.
+#[derive(Debug, Component)]
+pub struct PowerNode {
+    pub name: String,
+    source: bool,
+    requires: Option<ItemCheck>,
+    /// Power reaches the node.
+    pub powered: bool,
+    /// Power reaches the node and goes through it.
+    live: bool,
+}
+
+impl PowerNode {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: false,
+            requires: None,
+            powered: true,
+            live: true,
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Switch {
+    flag: String,
+    kind: SwitchKind,
+    player_near: bool,
+}
+
+#[derive(Debug, Component)]
+struct LeverHandle;
+
+#[derive(Debug, Component)]
+pub struct Door {
+    pub open: bool,
+    closed_position: Vec2,
+    /// Movement of the door from closed to fully open.
+    open_offset: Vec2,
+    requires: Option<ItemCheck>,
+    progress: f32,
+}
+
+#[derive(Debug, Component)]
+struct PoweredLight {
+    intensity: f32,
+}
+
+#[derive(Debug, Component)]
+struct Wire(String, String);
+
+/// Sent each time a door starts opening or closing.
+#[derive(Debug, Clone, Event)]
+pub struct DoorEvent {
+    pub door: Entity,
+    pub name: String,
+    pub open: bool,
+}
+
+/// Marks the machines spawned from the level data.
+#[derive(Debug, Component)]
+pub struct Machinery;
+
+impl Plugin for MachineryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DoorEvent>()
+            .add_systems(Update, spawn_machinery.after(SpawnLevelSet))
+            .add_systems(
+                Update,
+                (
+                    switch_proximity,
+                    use_switch.run_if(in_state(AppState::InGame)),
+                    update_power,
+                    (
+                        update_doors,
+                        update_switches,
+                        update_wires,
+                        update_powered_lights,
+                    ),
+                    door_sounds,
+                )
+                    .chain()
+                    .in_set(MachinerySet)
+                    .after(spawn_machinery),
+            );
+    }
+}
+
+fn check_items(requires: Option<&ItemCheck>, inventory: Option<&Inventory>) -> bool {
+    requires.is_none_or(|check| inventory.is_some_and(|inventory| check.check(inventory)))
+}
+
+/// Spawns the machinery of the current level each time it is built.
+pub fn spawn_machinery(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    other_nodes: Query<(&PowerNode, &Transform), Without<Machinery>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+    let spec = &level.machinery;
+    let position = |(x, y): (f32, f32)| Vec2::new(x, y);
+
+    let mut positions: HashMap<&str, Vec2> = other_nodes
+        .iter()
+        .map(|(node, transform)| (node.name.as_str(), transform.translation.truncate()))
+        .collect();
+
+    for switch in spec.switches.iter() {
+        positions.insert(&switch.name, position(switch.position));
+        commands
+            .spawn((
+                Name::new(switch.name.clone()),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.8, 0.2, 0.1),
+                        custom_size: Some(Vec2::new(6.0, 4.0)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(position(switch.position).extend(0.1)),
+                    ..Default::default()
+                },
+                Collider::ball(20.0),
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                Switch {
+                    flag: switch.flag.clone(),
+                    kind: switch.kind,
+                    player_near: false,
+                },
+                PowerNode::new(switch.name.clone()),
+                Machinery,
+                LevelEntity,
+            ))
+            .with_children(|commands| {
+                if switch.kind == SwitchKind::Lever {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgb(0.6, 0.6, 0.6),
+                                custom_size: Some(Vec2::new(2.0, 8.0)),
+                                anchor: Anchor::BottomCenter,
+                                ..Default::default()
+                            },
+                            transform: Transform::from_xyz(0.0, 0.0, 0.01)
+                                .with_rotation(Quat::from_rotation_z(LEVER_ANGLE)),
+                            ..Default::default()
+                        },
+                        LeverHandle,
+                    ));
+                }
+            });
+    }
+
+    for node in spec.nodes.iter() {
+        positions.insert(&node.name, position(node.position));
+        commands.spawn((
+            Name::new(node.name.clone()),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.25, 0.25, 0.3),
+                    custom_size: Some(Vec2::splat(5.0)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position(node.position).extend(0.03)),
+                ..Default::default()
+            },
+            PowerNode {
+                source: node.source,
+                requires: node.requires.clone(),
+                ..PowerNode::new(node.name.clone())
+            },
+            Machinery,
+            LevelEntity,
+        ));
+    }
+
+    for door in spec.doors.iter() {
+        let size = position(door.size);
+        positions.insert(&door.name, position(door.position));
+        commands.spawn((
+            Name::new(door.name.clone()),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.35, 0.4, 0.45),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position(door.position).extend(0.05)),
+                ..Default::default()
+            },
+            RigidBody::KinematicPositionBased,
+            Collider::cuboid(size.x / 2.0, size.y / 2.0),
+            Door {
+                open: false,
+                closed_position: position(door.position),
+                open_offset: if size.x > size.y {
+                    Vec2::new(size.x, 0.0)
+                } else {
+                    Vec2::new(0.0, size.y)
+                },
+                requires: door.requires.clone(),
+                progress: 0.0,
+            },
+            PowerNode::new(door.name.clone()),
+            Machinery,
+            LevelEntity,
+        ));
+    }
+
+    for light in spec.lights.iter() {
+        positions.insert(&light.name, position(light.position));
+        let (r, g, b) = light.color;
+        commands.spawn((
+            Name::new(light.name.clone()),
+            SpatialBundle::from_transform(Transform::from_translation(
+                position(light.position).extend(0.1),
+            )),
+            PointLight2d {
+                color: Color::rgb(r, g, b),
+                radius: light.radius,
+                intensity: light.intensity,
+                ..Default::default()
+            },
+            PoweredLight {
+                intensity: light.intensity,
+            },
+            PowerNode::new(light.name.clone()),
+            Machinery,
+            LevelEntity,
+        ));
+    }
+
+    for (from, to) in spec.wires.iter() {
+        let (Some(start), Some(end)) = (positions.get(from.as_str()), positions.get(to.as_str()))
+        else {
+            warn!("Wire between unknown machines {from} and {to}");
+            continue;
+        };
+
+        let delta = *end - *start;
+        commands.spawn((
+            Name::new(format!("Wire {from} - {to}")),
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(delta.length(), 1.0)),
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: ((*start + *end) / 2.0).extend(0.02),
+                    rotation: Quat::from_rotation_z(delta.y.atan2(delta.x)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Wire(from.clone(), to.clone()),
+            Machinery,
+            LevelEntity,
+        ));
+    }
+}
+
+fn switch_proximity(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut switches: Query<&mut Switch>,
+    player: Query<Entity, With<PlayerTag>>,
+    parents: Query<&Parent>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for collision_event in collision_events.read() {
+        let (entity1, entity2, near) = match *collision_event {
+            CollisionEvent::Started(entity1, entity2, _) => (entity1, entity2, true),
+            CollisionEvent::Stopped(entity1, entity2, _) => (entity1, entity2, false),
+        };
+
+        let switch = if is_player(entity2) {
+            entity1
+        } else if is_player(entity1) {
+            entity2
+        } else {
+            continue;
+        };
+
+        if let Ok(mut switch) = switches.get_mut(switch) {
+            switch.player_near = near;
+        }
+    }
+}
+
+fn use_switch(
+    actions: Res<ActionState>,
+    switches: Query<&Switch>,
+    mut story_flags: ResMut<StoryFlags>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    if !actions.just_pressed(Action::Interact) {
+        return;
+    }
+
+    for switch in switches.iter().filter(|switch| switch.player_near) {
+        if story_flags.is_set(&switch.flag) {
+            story_flags.unset(&switch.flag);
+        } else {
+            story_flags.set(&switch.flag);
+        }
+        sounds.send(PlaySound::new(Sound::SwitchClick, Bus::Sfx));
+    }
+}
+
+/// Spreads the power from the sources along the wires, stopping at the switches that are off
+/// and the nodes whose item check fails.
+fn update_power(
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+    story_flags: Res<StoryFlags>,
+    player: Query<&Inventory, With<PlayerTag>>,
+    mut nodes: Query<(&mut PowerNode, Option<&Switch>)>,
+) {
+    let wires = levels
+        .get(&level_asset.0)
+        .map_or(&[][..], |level| &level.machinery.wires[..]);
+    let inventory = player.get_single().ok();
+
+    let conducts: HashMap<String, bool> = nodes
+        .iter()
+        .map(|(node, switch)| {
+            let switch_on = switch.is_none_or(|switch| story_flags.is_set(&switch.flag));
+            let items_ok = check_items(node.requires.as_ref(), inventory);
+            (node.name.clone(), switch_on && items_ok)
+        })
+        .collect();
+    let mut queue: Vec<&str> = nodes
+        .iter()
+        .filter(|(node, _)| node.source)
+        .filter_map(|(node, _)| conducts.get_key_value(&node.name))
+        .filter(|(_, conducts)| **conducts)
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    let mut powered = HashSet::new();
+    while let Some(name) = queue.pop() {
+        if !powered.insert(name) || !conducts.get(name).copied().unwrap_or(false) {
+            continue;
+        }
+        for (from, to) in wires.iter() {
+            if from == name {
+                queue.push(to);
+            } else if to == name {
+                queue.push(from);
+            }
+        }
+    }
+
+    for (mut node, _) in nodes.iter_mut() {
+        let wired = wires
+            .iter()
+            .any(|(from, to)| *from == node.name || *to == node.name);
+        let is_powered = !wired || powered.contains(node.name.as_str());
+        let is_live = is_powered && conducts[&node.name];
+        if node.powered != is_powered || node.live != is_live {
+            node.powered = is_powered;
+            node.live = is_live;
+        }
+    }
+}
+
+/// Doors open while they are powered and the player has the items they require.
+fn update_doors(
+    time: Res<Time>,
+    player: Query<&Inventory, With<PlayerTag>>,
+    mut doors: Query<(Entity, &mut Door, &PowerNode, &mut Transform)>,
+    mut events: EventWriter<DoorEvent>,
+) {
+    let inventory = player.get_single().ok();
+
+    for (entity, mut door, node, mut transform) in doors.iter_mut() {
+        let open = node.powered && check_items(door.requires.as_ref(), inventory);
+        if door.open != open {
+            door.open = open;
+            events.send(DoorEvent {
+                door: entity,
+                name: node.name.clone(),
+                open,
+            });
+        }
+
+        let step = time.delta_seconds() / DOOR_DURATION;
+        let progress = if door.open {
+            (door.progress + step).min(1.0)
+        } else {
+            (door.progress - step).max(0.0)
+        };
+        if progress == door.progress {
+            continue;
+        }
+
+        door.progress = progress;
+        let t = progress * progress * (3.0 - 2.0 * progress);
+        let position = door.closed_position + door.open_offset * t;
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Switches are green while on and red while off, darker without power. Lever handles swing to
+/// the side of their state.
+fn update_switches(
+    time: Res<Time>,
+    story_flags: Res<StoryFlags>,
+    mut switches: Query<(&Switch, &PowerNode, &mut Sprite, Option<&Children>)>,
+    mut handles: Query<&mut Transform, With<LeverHandle>>,
+) {
+    for (switch, node, mut sprite, children) in switches.iter_mut() {
+        let on = story_flags.is_set(&switch.flag);
+        let color = if on {
+            Color::rgb(0.2, 0.8, 0.3)
+        } else {
+            Color::rgb(0.8, 0.2, 0.1)
+        };
+        sprite.color = if node.powered { color } else { color * 0.3 };
+
+        if switch.kind != SwitchKind::Lever {
+            continue;
+        }
+        let target = if on { -LEVER_ANGLE } else { LEVER_ANGLE };
+        for child in children.into_iter().flatten() {
+            if let Ok(mut transform) = handles.get_mut(*child) {
+                let angle = transform.rotation.to_euler(EulerRot::XYZ).2;
+                let step = LEVER_SPEED * time.delta_seconds();
+                transform.rotation =
+                    Quat::from_rotation_z(angle + (target - angle).clamp(-step, step));
+            }
+        }
+    }
+}
+
+/// Wires light up while they carry power.
+fn update_wires(nodes: Query<&PowerNode>, mut wires: Query<(&Wire, &mut Sprite)>) {
+    let live: HashSet<&str> = nodes
+        .iter()
+        .filter(|node| node.live)
+        .map(|node| node.name.as_str())
+        .collect();
+
+    for (Wire(from, to), mut sprite) in wires.iter_mut() {
+        sprite.color = if live.contains(from.as_str()) || live.contains(to.as_str()) {
+            Color::rgb(0.3, 0.9, 0.8)
+        } else {
+            Color::rgb(0.15, 0.15, 0.18)
+        };
+    }
+}
+
+fn update_powered_lights(mut lights: Query<(&PoweredLight, &PowerNode, &mut PointLight2d)>) {
+    for (light, node, mut point_light) in lights.iter_mut() {
+        let intensity = if node.powered { light.intensity } else { 0.0 };
+        if point_light.intensity != intensity {
+            point_light.intensity = intensity;
+        }
+    }
+}
+
+fn door_sounds(
+    mut events: EventReader<DoorEvent>,
+    doors: Query<&GlobalTransform>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for event in events.read() {
+        let action = if event.open { "opening" } else { "closing" };
+        info!("Door {} {action}", event.name);
+
+        let Ok(transform) = doors.get(event.door) else {
+            continue;
+        };
+        sounds.send(PlaySound {
+            sound: Sound::DoorSlide,
+            bus: Bus::Sfx,
+            position: Some((transform.translation().truncate(), DOOR_SOUND_RANGE)),
+        });
+    }
+}
@@ -1,70 +1,129 @@
+mod actions;
+mod animation;
 mod asset_enum;
+mod audio;
+mod camera;
+mod checkpoint;
+mod companion;
+mod creature;
+mod cutscene;
 mod dialog;
 mod forcefield;
+mod hazard;
+mod health;
+mod inventory;
+mod level;
+mod lighting;
+mod machinery;
+mod menu;
 pub mod objects;
-mod old;
 mod paralax;
 mod particles;
 mod player;
+mod save;
+mod settings;
+mod storage;
+mod story;
+mod suit;
 mod tilemap;
 
-use bevy::input::keyboard::KeyboardInput;
-use bevy::input::ButtonState;
 use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
-use bevy::transform::TransformSystem;
-use bevy::window::WindowResolution;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier2d::prelude::*;
 
-use self::dialog::{CurrentDialog, Dialog, DialogPlugin};
+use self::actions::{Action, ActionState, ActionsPlugin};
+use self::animation::SpriteAnimationPlugin;
+use self::audio::SoundPlugin;
+use self::camera::effects::CameraEffectsPlugin;
+use self::camera::{CameraController, CameraPlugin, CameraTarget};
+use self::checkpoint::CheckpointPlugin;
+use self::companion::CompanionPlugin;
+use self::creature::CreaturePlugin;
+use self::cutscene::CutscenePlugin;
+use self::dialog::DialogPlugin;
+use self::forcefield::ForceFieldPlugin;
+use self::hazard::HazardPlugin;
+use self::health::HealthPlugin;
+use self::inventory::InventoryPlugin;
+use self::level::{LevelPlugin, SpawnLevelSet};
+use self::lighting::LightingPlugin;
+use self::machinery::MachineryPlugin;
+use self::menu::MenuPlugin;
 use self::objects::ObjectsPlugin;
-use self::paralax::{paralax_movement, ParalaxBackground, ParalaxTarget};
-use self::player::{PlayerBundle, PlayerTag};
+use self::paralax::ParalaxPlugin;
+use self::particles::ParticlePlugin;
+use self::player::animation::PlayerAnimationPlugin;
+use self::player::movement::MovementPlugin;
+use self::player::PlayerBundle;
+use self::save::SavePlugin;
+use self::settings::{Settings, SettingsPlugin};
+use self::story::StoryFlags;
+use self::suit::SuitPlugin;
 use self::tilemap::spawn_map;
 
 #[derive(Debug, States, Default, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum AppState {
     #[default]
+    MainMenu,
     InGame,
     InDialog,
+    Cutscene,
+    Paused,
 }
 
 fn main() {
+    let settings = SettingsPlugin::read();
     App::new()
         .add_plugins(
             DefaultPlugins
+                .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        resolution: WindowResolution::new(1280.0, 720.0),
-                        // present_mode: PresentMode::Immediate,
-                        ..Default::default()
-                    }),
+                    primary_window: Some(settings.window()),
                     ..Default::default()
-                })
-                .set(ImagePlugin::default_nearest()),
+                }),
         )
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(16.0))
         // .add_plugins(WorldInspectorPlugin::default())
         .add_plugins(RapierDebugRenderPlugin::default().disabled())
+        .add_plugins(settings)
+        .add_plugins(ActionsPlugin)
+        .add_plugins(CameraPlugin)
+        .add_plugins(CameraEffectsPlugin)
         .add_plugins(DialogPlugin)
+        .add_plugins(CutscenePlugin)
         .add_plugins(ObjectsPlugin)
+        .add_plugins(SpriteAnimationPlugin)
+        .add_plugins(PlayerAnimationPlugin)
+        .add_plugins(MovementPlugin)
+        .add_plugins(ParticlePlugin)
+        .add_plugins(LevelPlugin)
+        .add_plugins(ParalaxPlugin)
+        .add_plugins(LightingPlugin)
+        .add_plugins(CheckpointPlugin)
+        .add_plugins(ForceFieldPlugin)
+        .add_plugins(MachineryPlugin)
+        .add_plugins(HazardPlugin)
+        .add_plugins(HealthPlugin)
+        .add_plugins(SuitPlugin)
+        .add_plugins(CreaturePlugin)
+        .add_plugins(CompanionPlugin)
+        .add_plugins(InventoryPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(SoundPlugin)
+        .add_plugins(MenuPlugin)
         .add_state::<AppState>()
         .add_event::<ResetEvent>()
-        .add_systems(Startup, (setup, spawn_map))
+        .init_resource::<StoryFlags>()
+        .add_systems(Startup, setup)
         .add_systems(
             Update,
             (
-                (movement, reset).run_if(in_state(AppState::InGame)),
+                spawn_map.in_set(SpawnLevelSet),
+                reset.run_if(in_state(AppState::InGame)),
                 control_debug_renderer,
             ),
         )
-        .add_systems(
-            PostUpdate,
-            paralax_movement
-                .after(PhysicsSet::Writeback)
-                .before(TransformSystem::TransformPropagate),
-        )
         .insert_resource(RapierConfiguration {
             gravity: Vec2::ZERO,
             ..Default::default()
@@ -72,101 +131,39 @@ fn main() {
         .run();
 }
 
-pub fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
-    mut current_dialog: ResMut<CurrentDialog>,
-    mut state: ResMut<NextState<AppState>>,
-) {
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
     commands
         .spawn(Camera2dBundle {
-            transform: Transform::from_translation(Vec3::new(200.0, -300.0, 10.0)),
+            transform: Transform::from_xyz(0.0, 0.0, 10.0),
             projection: OrthographicProjection {
-                scaling_mode: ScalingMode::WindowSize(3.0),
+                scaling_mode: ScalingMode::WindowSize(settings.camera_scale),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(ParalaxBackground {
-            paralax_factor: 1.0,
-        });
-    let player_atlas = TextureAtlas::from_grid(
-        asset_server.load("texture/player.png"),
-        Vec2::splat(32.0),
-        4,
-        1,
-        None,
-        None,
-    );
-    let player_atlas = atlases.add(player_atlas);
+        .insert(CameraController::default());
     commands
         .spawn(PlayerBundle::new(
-            Transform::from_translation(Vec3::new(200.0, -300.0, 1.0)),
-            player_atlas,
+            // Moved to the start of the level once it is loaded
+            Transform::from_xyz(0.0, 0.0, 1.0),
+            asset_server.load("texture/player.atlas.ron"),
+            asset_server.load("texture/player.atlas.ron#atlas"),
         ))
-        .insert(ParalaxTarget)
+        .insert(CameraTarget)
         .with_children(|commands| {
             commands.spawn((
                 SpatialBundle::from_transform(Transform::from_xyz(0.0, -9.5, 0.0)),
                 Collider::cuboid(6.5, 2.5),
+                ActiveEvents::COLLISION_EVENTS,
             ));
         });
-
-    current_dialog.set(Dialog::Intro);
-    state.set(AppState::InDialog);
-}
-
-const RCS_FORCE: f32 = 100.0;
-
-pub fn movement(
-    time: Res<Time>,
-    mut player_impulse: Query<(&mut ExternalImpulse, &mut TextureAtlasSprite), With<PlayerTag>>,
-    inputs: Res<Input<KeyCode>>,
-) {
-    let (mut impulse, mut sprite) = player_impulse.single_mut();
-
-    let mut add_impulse = Vec2::default();
-
-    if inputs.pressed(KeyCode::S) {
-        add_impulse.y -= 1.0;
-        sprite.index = 0;
-    }
-
-    if inputs.pressed(KeyCode::W) {
-        add_impulse.y += 1.0;
-        sprite.index = 2;
-    }
-
-    if inputs.pressed(KeyCode::A) {
-        add_impulse.x -= 1.0;
-        sprite.index = 3;
-    }
-
-    if inputs.pressed(KeyCode::D) {
-        add_impulse.x += 1.0;
-        sprite.index = 1;
-    }
-
-    if add_impulse != Vec2::default() {
-        impulse.impulse += add_impulse.normalize() * RCS_FORCE * time.delta_seconds();
-    }
 }
 
 pub fn control_debug_renderer(
-    mut events: EventReader<KeyboardInput>,
+    actions: Res<ActionState>,
     mut debug_render_context: ResMut<DebugRenderContext>,
 ) {
-    for _ in events.read().filter(|input| {
-        matches!(
-            input,
-            KeyboardInput {
-                state: ButtonState::Pressed,
-                key_code: Some(KeyCode::F1),
-                ..
-            }
-        )
-    }) {
+    if actions.just_pressed(Action::ToggleDebug) {
         debug_render_context.enabled = !debug_render_context.enabled;
     }
 }
@@ -174,29 +171,8 @@ pub fn control_debug_renderer(
 #[derive(Debug, Default, Event)]
 pub struct ResetEvent;
 
-pub fn reset(
-    mut events: EventReader<KeyboardInput>,
-    mut player: Query<(&mut Transform, &mut Velocity), With<PlayerTag>>,
-    mut current_dialog: ResMut<CurrentDialog>,
-    mut state: ResMut<NextState<AppState>>,
-    mut event: EventWriter<ResetEvent>,
-) {
-    for _ in events.read().filter(|input| {
-        matches!(
-            input,
-            KeyboardInput {
-                state: ButtonState::Pressed,
-                key_code: Some(KeyCode::R),
-                ..
-            }
-        )
-    }) {
-        let (mut transform, mut velocity) = player.single_mut();
-        *transform = Transform::from_translation(Vec3::new(200.0, -300.0, 1.0));
-        *velocity = Default::default();
-
-        current_dialog.set(Dialog::Intro);
-        state.set(AppState::InDialog);
+pub fn reset(actions: Res<ActionState>, mut event: EventWriter<ResetEvent>) {
+    if actions.just_pressed(Action::Reset) {
         event.send_default();
     }
 }
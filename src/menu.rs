@@ -0,0 +1,603 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use bevy_rapier2d::prelude::*;
+
+use crate::actions::{Action, ActionMap, ActionState, Rebinding};
+use crate::audio::{Bus, VolumeSettings};
+use crate::camera::effects::CameraEffect;
+use crate::checkpoint::LastCheckpoint;
+use crate::cutscene::{CurrentCutscene, Cutscene};
+use crate::health::{Dying, Health};
+use crate::inventory::Inventory;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevel, CurrentLevelAsset};
+use crate::objects::FiredTriggers;
+use crate::player::PlayerTag;
+use crate::save::{slot_exists, LoadEvent, QUICK_SAVE_SLOT};
+use crate::settings::{Language, Settings};
+use crate::story::StoryFlags;
+use crate::suit::Suit;
+use crate::AppState;
+
+const RESOLUTIONS: &[(f32, f32)] = &[(1280.0, 720.0), (1600.0, 900.0), (1920.0, 1080.0)];
+const WINDOW_MODES: &[WindowMode] = &[
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen,
+];
+const UI_SCALES: &[f64] = &[0.75, 1.0, 1.25, 1.5];
+const CAMERA_SCALES: &[f32] = &[2.0, 3.0, 4.0];
+const VOLUME_STEP: f32 = 0.1;
+
+const BUTTON_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.8);
+const SELECTED_COLOR: Color = Color::rgba(0.0, 0.45, 0.55, 0.9);
+
+pub struct MenuPlugin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Main,
+    Pause,
+    Settings,
+    Bindings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+enum MenuAction {
+    NewGame,
+    Continue,
+    Resume,
+    Settings,
+    Bindings,
+    MainMenu,
+    Quit,
+    Back,
+    Resolution,
+    WindowMode,
+    VSync,
+    UiScale,
+    CameraScale,
+    /// Volume of a bus, or the master volume for `None`.
+    Volume(Option<Bus>),
+    Language,
+    Rebind(Action),
+}
+
+/// The screens opened in the current menu, the last one being displayed.
+#[derive(Debug, Default, Resource)]
+struct Menu {
+    screens: Vec<Screen>,
+    selected: usize,
+}
+
+impl Menu {
+    fn open(&mut self, screen: Screen) {
+        self.screens.push(screen);
+        self.selected = 0;
+    }
+
+    fn back(&mut self) {
+        if self.screens.len() > 1 {
+            self.screens.pop();
+            self.selected = 0;
+        }
+    }
+}
+
+/// The state to go back to when resuming the game.
+#[derive(Debug, Default, Resource)]
+struct PausedFrom(Option<AppState>);
+
+#[derive(Debug, Component)]
+struct MenuRoot;
+
+#[derive(Debug, Component)]
+struct MenuButton {
+    action: MenuAction,
+    index: usize,
+}
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        let in_menu = in_state(AppState::MainMenu).or_else(in_state(AppState::Paused));
+
+        app.init_resource::<Menu>()
+            .init_resource::<PausedFrom>()
+            .init_resource::<Language>()
+            .add_event::<MenuAction>()
+            .add_systems(OnEnter(AppState::MainMenu), (open_main_menu, stop_time))
+            .add_systems(OnEnter(AppState::Paused), (open_pause_menu, stop_time))
+            .add_systems(OnExit(AppState::MainMenu), (close_menu, resume_time))
+            .add_systems(OnExit(AppState::Paused), (close_menu, resume_time))
+            .add_systems(
+                Update,
+                (
+                    pause_input
+                        .run_if(in_state(AppState::InGame).or_else(in_state(AppState::InDialog))),
+                    (
+                        menu_input,
+                        menu_mouse,
+                        apply_menu_actions,
+                        build_menu,
+                        highlight_selected,
+                    )
+                        .chain()
+                        .run_if(in_menu),
+                ),
+            );
+    }
+}
+
+fn open_main_menu(mut menu: ResMut<Menu>) {
+    menu.screens.clear();
+    menu.open(Screen::Main);
+}
+
+fn open_pause_menu(mut menu: ResMut<Menu>) {
+    menu.screens.clear();
+    menu.open(Screen::Pause);
+}
+
+fn close_menu(
+    mut commands: Commands,
+    mut menu: ResMut<Menu>,
+    mut rebinding: ResMut<Rebinding>,
+    roots: Query<Entity, With<MenuRoot>>,
+) {
+    menu.screens.clear();
+    rebinding.0 = None;
+    for entity in roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn stop_time(mut time: ResMut<Time<Virtual>>, mut rapier: ResMut<RapierConfiguration>) {
+    time.pause();
+    rapier.physics_pipeline_active = false;
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>, mut rapier: ResMut<RapierConfiguration>) {
+    time.unpause();
+    rapier.physics_pipeline_active = true;
+}
+
+fn pause_input(
+    actions: Res<ActionState>,
+    state: Res<State<AppState>>,
+    mut paused_from: ResMut<PausedFrom>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        paused_from.0 = Some(*state.get());
+        next_state.set(AppState::Paused);
+    }
+}
+
+fn menu_input(
+    actions: Res<ActionState>,
+    mut menu: ResMut<Menu>,
+    buttons: Query<&MenuButton>,
+    mut menu_actions: EventWriter<MenuAction>,
+) {
+    let count = buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    if actions.just_pressed(Action::ThrustDown) {
+        menu.selected = (menu.selected + 1) % count;
+    }
+    if actions.just_pressed(Action::ThrustUp) {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+
+    if actions.just_pressed(Action::Advance) || actions.just_pressed(Action::Interact) {
+        if let Some(button) = buttons.iter().find(|button| button.index == menu.selected) {
+            menu_actions.send(button.action);
+        }
+    }
+
+    if actions.just_pressed(Action::Pause) {
+        menu_actions.send(MenuAction::Back);
+    }
+}
+
+fn menu_mouse(
+    mut menu: ResMut<Menu>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut menu_actions: EventWriter<MenuAction>,
+) {
+    for (interaction, button) in buttons.iter() {
+        match interaction {
+            Interaction::Pressed => menu_actions.send(button.action),
+            Interaction::Hovered if menu.selected != button.index => {
+                menu.selected = button.index;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_menu_actions(
+    mut commands: Commands,
+    mut events: EventReader<MenuAction>,
+    mut menu: ResMut<Menu>,
+    paused_from: Res<PausedFrom>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut load_events: EventWriter<LoadEvent>,
+    mut app_exit: EventWriter<AppExit>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+    mut settings: ResMut<Settings>,
+    mut volume: ResMut<VolumeSettings>,
+    mut language: ResMut<Language>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for action in events.read() {
+        match *action {
+            MenuAction::NewGame => commands.add(start_new_game),
+            MenuAction::Continue => load_events.send(LoadEvent(QUICK_SAVE_SLOT)),
+            MenuAction::Resume => {
+                next_state.set(paused_from.0.unwrap_or(AppState::InGame));
+            }
+            MenuAction::Settings => menu.open(Screen::Settings),
+            MenuAction::Bindings => menu.open(Screen::Bindings),
+            MenuAction::MainMenu => next_state.set(AppState::MainMenu),
+            MenuAction::Quit => app_exit.send(AppExit),
+            MenuAction::Back => {
+                if menu.screens.last() == Some(&Screen::Pause) {
+                    next_state.set(paused_from.0.unwrap_or(AppState::InGame));
+                } else {
+                    menu.back();
+                }
+            }
+            MenuAction::Resolution => {
+                if let Ok(mut window) = windows.get_single_mut() {
+                    let current = (window.resolution.width(), window.resolution.height());
+                    let index = RESOLUTIONS
+                        .iter()
+                        .position(|resolution| *resolution == current)
+                        .map_or(0, |index| (index + 1) % RESOLUTIONS.len());
+                    let (width, height) = RESOLUTIONS[index];
+                    window.resolution.set(width, height);
+                }
+            }
+            MenuAction::WindowMode => {
+                if let Ok(mut window) = windows.get_single_mut() {
+                    let index = WINDOW_MODES
+                        .iter()
+                        .position(|mode| *mode == window.mode)
+                        .map_or(0, |index| (index + 1) % WINDOW_MODES.len());
+                    window.mode = WINDOW_MODES[index];
+                }
+            }
+            MenuAction::VSync => {
+                if let Ok(mut window) = windows.get_single_mut() {
+                    window.present_mode = match window.present_mode {
+                        PresentMode::AutoNoVsync => PresentMode::AutoVsync,
+                        _ => PresentMode::AutoNoVsync,
+                    };
+                }
+            }
+            MenuAction::UiScale => {
+                let index = UI_SCALES
+                    .iter()
+                    .position(|scale| *scale == ui_scale.0)
+                    .map_or(0, |index| (index + 1) % UI_SCALES.len());
+                ui_scale.0 = UI_SCALES[index];
+            }
+            MenuAction::CameraScale => {
+                let index = CAMERA_SCALES
+                    .iter()
+                    .position(|scale| *scale == settings.camera_scale)
+                    .map_or(0, |index| (index + 1) % CAMERA_SCALES.len());
+                settings.camera_scale = CAMERA_SCALES[index];
+            }
+            MenuAction::Volume(bus) => {
+                let value = match bus {
+                    None => &mut volume.master,
+                    Some(Bus::Music) => &mut volume.music,
+                    Some(Bus::Sfx) => &mut volume.sfx,
+                    Some(Bus::Dialog) => &mut volume.dialog,
+                };
+                // Goes back to silent after the maximum
+                *value = if *value >= 1.0 - VOLUME_STEP / 2.0 {
+                    0.0
+                } else {
+                    ((*value + VOLUME_STEP) / VOLUME_STEP).round() * VOLUME_STEP
+                };
+            }
+            MenuAction::Language => *language = language.next(),
+            MenuAction::Rebind(action) => rebinding.0 = Some(action),
+        }
+    }
+}
+
+/// Puts the world back as it is at the start of the game and plays the intro.
+fn start_new_game(world: &mut World) {
+    let level_asset = &world.resource::<CurrentLevelAsset>().0;
+    let start_position = world
+        .resource::<Assets<LevelAsset>>()
+        .get(level_asset)
+        .map(LevelAsset::start_position);
+    let mut player = world.query_filtered::<(
+        &mut Transform,
+        &mut Velocity,
+        &mut Inventory,
+        &mut Health,
+        &mut Suit,
+    ), With<PlayerTag>>();
+    for (mut transform, mut velocity, mut inventory, mut health, mut suit) in player.iter_mut(world)
+    {
+        if let Some(start_position) = start_position {
+            transform.translation = start_position.extend(transform.translation.z);
+        }
+        *velocity = Velocity::zero();
+        *inventory = Inventory::default();
+        health.current = health.max;
+        suit.refill();
+    }
+
+    // A new game started while the screen fades out on death must not respawn the player
+    let mut dying = world.query_filtered::<Entity, (With<PlayerTag>, With<Dying>)>();
+    for entity in dying.iter(world).collect::<Vec<_>>() {
+        world.entity_mut(entity).remove::<Dying>();
+        world.send_event(CameraEffect::Fade {
+            to: 0.0,
+            duration: 0.0,
+        });
+    }
+
+    *world.resource_mut::<StoryFlags>() = StoryFlags::default();
+    world.resource_mut::<FiredTriggers>().0.clear();
+    world.insert_resource(LastCheckpoint::default());
+    // Rebuilds the level
+    world.resource_mut::<CurrentLevel>().set_changed();
+
+    world.resource_mut::<CurrentCutscene>().set(Cutscene::Intro);
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Cutscene);
+}
+
+fn percent(volume: f32) -> String {
+    format!("{}%", (volume * 100.0).round())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn button_labels(
+    screen: Screen,
+    window: Option<&Window>,
+    ui_scale: f64,
+    camera_scale: f32,
+    volume: &VolumeSettings,
+    language: Language,
+    action_map: &ActionMap,
+    rebinding: &Rebinding,
+) -> Vec<(MenuAction, String)> {
+    let label = |action, text: &str| (action, text.to_string());
+
+    match screen {
+        Screen::Main => {
+            let mut labels = vec![label(MenuAction::NewGame, "New game")];
+            if slot_exists(QUICK_SAVE_SLOT) {
+                labels.push(label(MenuAction::Continue, "Continue"));
+            }
+            labels.push(label(MenuAction::Settings, "Settings"));
+            labels.push(label(MenuAction::Quit, "Quit"));
+            labels
+        }
+        Screen::Pause => vec![
+            label(MenuAction::Resume, "Resume"),
+            label(MenuAction::Settings, "Settings"),
+            label(MenuAction::MainMenu, "Main menu"),
+            label(MenuAction::Quit, "Quit"),
+        ],
+        Screen::Settings => {
+            let mut labels = vec![];
+            if let Some(window) = window {
+                labels.push((
+                    MenuAction::Resolution,
+                    format!(
+                        "Resolution: {}x{}",
+                        window.resolution.width(),
+                        window.resolution.height()
+                    ),
+                ));
+                labels.push((
+                    MenuAction::WindowMode,
+                    format!("Window mode: {:?}", window.mode),
+                ));
+                let vsync = window.present_mode != PresentMode::AutoNoVsync;
+                labels.push((
+                    MenuAction::VSync,
+                    format!("VSync: {}", if vsync { "on" } else { "off" }),
+                ));
+            }
+            labels.extend([
+                (
+                    MenuAction::UiScale,
+                    format!("Interface scale: {}", percent(ui_scale as f32)),
+                ),
+                (
+                    MenuAction::CameraScale,
+                    format!("Camera zoom: {camera_scale}x"),
+                ),
+                (
+                    MenuAction::Volume(None),
+                    format!("Master volume: {}", percent(volume.master)),
+                ),
+                (
+                    MenuAction::Volume(Some(Bus::Music)),
+                    format!("Music volume: {}", percent(volume.music)),
+                ),
+                (
+                    MenuAction::Volume(Some(Bus::Sfx)),
+                    format!("Effects volume: {}", percent(volume.sfx)),
+                ),
+                (
+                    MenuAction::Volume(Some(Bus::Dialog)),
+                    format!("Dialog volume: {}", percent(volume.dialog)),
+                ),
+            ]);
+            if Language::AVAILABLE.len() > 1 {
+                labels.push((MenuAction::Language, format!("Language: {language:?}")));
+            }
+            labels.push(label(MenuAction::Bindings, "Key bindings"));
+            labels.push(label(MenuAction::Back, "Back"));
+            labels
+        }
+        Screen::Bindings => action_map
+            .bindings
+            .iter()
+            .map(|(action, bindings)| {
+                let text = if rebinding.0 == Some(*action) {
+                    format!("{action:?}: press a key or a button...")
+                } else {
+                    let bindings: Vec<_> = bindings
+                        .iter()
+                        .map(|binding| format!("{binding:?}"))
+                        .collect();
+                    format!("{action:?}: {}", bindings.join(", "))
+                };
+                (MenuAction::Rebind(*action), text)
+            })
+            .chain([label(MenuAction::Back, "Back")])
+            .collect(),
+    }
+}
+
+/// Spawns the current screen again each time what it displays changes.
+#[allow(clippy::too_many_arguments)]
+fn build_menu(
+    mut commands: Commands,
+    menu: Res<Menu>,
+    state: Res<State<AppState>>,
+    windows: Query<Ref<Window>, With<PrimaryWindow>>,
+    ui_scale: Res<UiScale>,
+    settings: Res<Settings>,
+    volume: Res<VolumeSettings>,
+    language: Res<Language>,
+    action_map: Res<ActionMap>,
+    rebinding: Res<Rebinding>,
+    roots: Query<Entity, With<MenuRoot>>,
+    mut built_screen: Local<Option<Screen>>,
+) {
+    let Some(&screen) = menu.screens.last() else {
+        return;
+    };
+
+    let window = windows.get_single().ok();
+    let changed = *built_screen != Some(screen)
+        || ui_scale.is_changed()
+        || settings.is_changed()
+        || volume.is_changed()
+        || language.is_changed()
+        || action_map.is_changed()
+        || rebinding.is_changed()
+        || window.as_ref().is_some_and(|window| window.is_changed());
+    if !changed && !roots.is_empty() {
+        return;
+    }
+    *built_screen = Some(screen);
+
+    for entity in roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let labels = button_labels(
+        screen,
+        window.as_deref(),
+        ui_scale.0,
+        settings.camera_scale,
+        &volume,
+        *language,
+        &action_map,
+        &rebinding,
+    );
+    let (title, background) = match (screen, state.get()) {
+        (Screen::Main, _) => ("The Lost Vessel", 0.95),
+        (Screen::Pause, _) => ("Paused", 0.7),
+        (Screen::Settings, AppState::MainMenu) => ("Settings", 0.95),
+        (Screen::Settings, _) => ("Settings", 0.7),
+        (Screen::Bindings, AppState::MainMenu) => ("Key bindings", 0.95),
+        (Screen::Bindings, _) => ("Key bindings", 0.7),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                background_color: Color::rgba(0.0, 0.0, 0.0, background).into(),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                z_index: ZIndex::Global(10),
+                ..Default::default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|commands| {
+            commands.spawn(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font_size: 48.0,
+                        ..Default::default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(32.0)),
+                    ..Default::default()
+                }),
+            );
+
+            for (index, (action, text)) in labels.into_iter().enumerate() {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            background_color: BUTTON_COLOR.into(),
+                            style: Style {
+                                width: Val::Px(560.0),
+                                height: Val::Px(44.0),
+                                margin: UiRect::all(Val::Px(4.0)),
+                                align_items: AlignItems::Center,
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        MenuButton { action, index },
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            text,
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn highlight_selected(menu: Res<Menu>, mut buttons: Query<(&MenuButton, &mut BackgroundColor)>) {
+    for (button, mut color) in buttons.iter_mut() {
+        let target = if button.index == menu.selected {
+            SELECTED_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        if color.0 != target {
+            color.0 = target;
+        }
+    }
+}
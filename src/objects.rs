@@ -1,24 +1,55 @@
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy_rapier2d::prelude::*;
 
+use crate::camera::CameraFocus;
+use crate::dialog::bubble::ShowBubble;
 use crate::dialog::{CurrentDialog, Dialog};
-use crate::{AppState, ResetEvent};
+use crate::inventory::{Inventory, ItemCheck};
+use crate::level::{LevelEntity, SpawnLevelSet};
+use crate::lighting::{LightAnimation, PointLight2d};
+use crate::player::PlayerTag;
+use crate::AppState;
 
 pub struct ObjectsPlugin;
 
 #[derive(Component)]
-struct DialogTrigger(Dialog, bool);
+struct DialogTrigger {
+    dialog: Dialog,
+    /// The trigger only fires once the player's items pass the check.
+    requires: Option<ItemCheck>,
+    /// The dialog is said in bubbles above the player instead of stopping the game.
+    bubbles: bool,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct FiredTriggers(pub HashSet<Dialog>);
 
 impl DialogTrigger {
     fn new(dialog: Dialog) -> Self {
-        Self(dialog, false)
+        Self {
+            dialog,
+            requires: None,
+            bubbles: false,
+        }
+    }
+
+    fn requires(mut self, check: ItemCheck) -> Self {
+        self.requires = Some(check);
+        self
+    }
+
+    fn in_bubbles(mut self) -> Self {
+        self.bubbles = true;
+        self
     }
 }
 
 impl Plugin for ObjectsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_objects)
-            .add_systems(Update, (trigger_check.run_if(in_state(AppState::InGame)), reset_check));
+        app.init_resource::<FiredTriggers>()
+            .add_systems(Update, spawn_objects.in_set(SpawnLevelSet))
+            .add_systems(Update, trigger_check.run_if(in_state(AppState::InGame)));
     }
 }
 
@@ -32,6 +63,7 @@ fn spawn_objects(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..Default::default()
             },
             RigidBody::Fixed,
+            LevelEntity,
         ))
         .with_children(|commands| {
             commands.spawn((
@@ -58,6 +90,18 @@ fn spawn_objects(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..Default::default()
             },
             RigidBody::Fixed,
+            // The energy signature of the plant
+            PointLight2d {
+                color: Color::rgb(0.4, 1.0, 0.5),
+                radius: 40.0,
+                intensity: 0.6,
+                animation: LightAnimation::Pulse {
+                    period: 3.0,
+                    amount: 0.5,
+                },
+                ..Default::default()
+            },
+            LevelEntity,
         ))
         .with_children(|commands| {
             commands.spawn((
@@ -74,37 +118,61 @@ fn spawn_objects(mut commands: Commands, asset_server: Res<AssetServer>) {
                 DialogTrigger::new(Dialog::Plant),
             ));
         });
+
+    commands.spawn((
+        Name::new("Storage thoughts sensor"),
+        SpatialBundle::from_transform(Transform::from_xyz(395.0, -340.0, 0.0)),
+        Collider::ball(24.0),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        // Only makes sense before the glowing fuse has been taken out of the storage
+        DialogTrigger::new(Dialog::ThoughtStorage)
+            .requires(ItemCheck::Missing("fuse".to_string()))
+            .in_bubbles(),
+        LevelEntity,
+    ));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn trigger_check(
     mut collision_events: EventReader<CollisionEvent>,
-    mut dialog_trigger: Query<&mut DialogTrigger>,
+    dialog_trigger: Query<&DialogTrigger>,
+    player: Query<(Entity, &Inventory), With<PlayerTag>>,
+    mut fired_triggers: ResMut<FiredTriggers>,
     mut current_dialog: ResMut<CurrentDialog>,
+    mut camera_focus: ResMut<CameraFocus>,
     mut state: ResMut<NextState<AppState>>,
+    mut bubbles: EventWriter<ShowBubble>,
 ) {
+    let Ok((player, inventory)) = player.get_single() else {
+        return;
+    };
+
     for collision_event in collision_events.read() {
         if let CollisionEvent::Started(entity1, entity2, _) = *collision_event {
-            if let Ok(mut dialog_trigger) = dialog_trigger.get_mut(entity1) {
-                if !dialog_trigger.1 {
-                    current_dialog.set(dialog_trigger.0);
-                    state.set(AppState::InDialog);
-                    dialog_trigger.1 = true;
+            if let Some((entity, dialog_trigger)) = [entity1, entity2]
+                .into_iter()
+                .find_map(|entity| Some((entity, dialog_trigger.get(entity).ok()?)))
+            {
+                let allowed = dialog_trigger
+                    .requires
+                    .as_ref()
+                    .is_none_or(|check| check.check(inventory));
+                if !allowed || !fired_triggers.0.insert(dialog_trigger.dialog) {
+                    continue;
                 }
-            } else if let Ok(mut dialog_trigger) = dialog_trigger.get_mut(entity2) {
-                if !dialog_trigger.1 {
-                    current_dialog.set(dialog_trigger.0);
+
+                if dialog_trigger.bubbles {
+                    bubbles.send(ShowBubble {
+                        speaker: player,
+                        dialog: dialog_trigger.dialog,
+                    });
+                } else {
+                    current_dialog.set(dialog_trigger.dialog);
+                    camera_focus.set(entity);
                     state.set(AppState::InDialog);
-                    dialog_trigger.1 = true;
                 }
             }
         }
     }
 }
-
-fn reset_check(mut events: EventReader<ResetEvent>, mut triggers: Query<&mut DialogTrigger>) {
-    for _ in events.read() {
-        for mut dialog in triggers.iter_mut() {
-            dialog.1 = false;
-        }
-    }
-}
@@ -1,28 +1,102 @@
-use bevy::ecs::query::QuerySingleError;
 use bevy::prelude::*;
+use bevy::render::texture::{
+    ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+use bevy::transform::TransformSystem;
 
-#[derive(Component)]
-pub struct ParalaxTarget;
+use crate::camera::CameraSet;
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
 
-#[derive(Component, Reflect)]
-pub struct ParalaxBackground {
+// Extra size of the layers around the view, hides the edges while the camera zooms
+const VIEW_MARGIN: f32 = 1.5;
+
+pub struct ParalaxPlugin;
+
+/// A background sprite covering the whole view whose texture repeats infinitely and scrolls
+/// according to the camera position.
+#[derive(Debug, Component, Reflect)]
+pub struct ParalaxLayer {
     pub paralax_factor: f32,
+    pub origin: Vec2,
+    pub scale: f32,
+}
+
+impl Plugin for ParalaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ParalaxLayer>()
+            .add_systems(Update, spawn_paralax_layers.after(SpawnLevelSet))
+            .add_systems(
+                PostUpdate,
+                paralax_movement
+                    .after(CameraSet)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn repeat_sampler(settings: &mut ImageLoaderSettings) {
+    settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::nearest()
+    });
+}
+
+/// Spawns the layers of the current level each time it is built.
+fn spawn_paralax_layers(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    asset_server: Res<AssetServer>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+
+    for spec in level.parallax.iter() {
+        commands.spawn((
+            Name::new(format!("Paralax {}", spec.texture)),
+            SpriteBundle {
+                texture: asset_server.load_with_settings(spec.texture.clone(), repeat_sampler),
+                transform: Transform::from_xyz(0.0, 0.0, spec.z),
+                ..Default::default()
+            },
+            ParalaxLayer {
+                paralax_factor: spec.factor,
+                origin: Vec2::new(spec.offset.0, spec.offset.1),
+                scale: spec.scale,
+            },
+            LevelEntity,
+        ));
+    }
 }
 
 pub fn paralax_movement(
-    target: Query<&Transform, (With<ParalaxTarget>, Changed<Transform>)>,
-    mut background: Query<(&mut Transform, &ParalaxBackground), Without<ParalaxTarget>>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut layers: Query<(&mut Transform, &mut Sprite, &ParalaxLayer), Without<Camera2d>>,
 ) {
-    match target.get_single() {
-        Ok(target_transform) => {
-            for (mut transform, ParalaxBackground { paralax_factor }) in background.iter_mut()  {
-                transform.translation.x = target_transform.translation.x * *paralax_factor;
-                transform.translation.y = target_transform.translation.y * *paralax_factor;
-            }
-        }
-        Err(QuerySingleError::MultipleEntities(..)) => {
-            error!("Multiple ParalaxTarget were found");
-        }
-        _ => {}
+    let Ok((camera, projection)) = camera.get_single() else {
+        return;
+    };
+
+    let view = projection.area.size() * VIEW_MARGIN;
+    let camera_position = camera.translation.truncate();
+    for (mut transform, mut sprite, layer) in layers.iter_mut() {
+        transform.translation.x = camera_position.x;
+        transform.translation.y = camera_position.y;
+
+        // The layer only moves by its factor with the camera, what is left scrolls the texture
+        let scroll = (camera_position * (1.0 - layer.paralax_factor) - layer.origin) / layer.scale;
+        sprite.custom_size = Some(view);
+        sprite.rect = Some(Rect::from_center_size(
+            Vec2::new(scroll.x, -scroll.y),
+            view / layer.scale,
+        ));
     }
 }
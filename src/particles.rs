@@ -2,26 +2,35 @@ use bevy::prelude::*;
 use bevy_hanabi::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::player::animation::Facing;
+use crate::player::movement::{movement, MovementModel, PlayerMovementModel};
 use crate::player::PlayerTag;
-use crate::old::player_movement;
 
-pub struct ParticlePlugin;
+// Distance from the center of the sprite to its edges, the feet being lower than the head
+const NOZZLE_EXTENTS: Vec2 = Vec2::new(10.0, 13.0);
+const GAS_SPEED: f32 = 40.0;
 
-#[derive(Component)]
-struct LeftRCS;
+pub struct ParticlePlugin;
 
-#[derive(Component)]
-struct RightRCS;
+/// Gas emitter of the player RCS, moved to the nozzle facing the gas direction.
+#[derive(Debug, Default, Component)]
+struct RcsEmitter {
+    // Accumulated fraction of frames the spawner should be active, scales the emission rate
+    emission: f32,
+}
 
 impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(HanabiPlugin)
             .add_systems(PostStartup, setup_particle_effect)
-            .add_systems(Update, rcs_particles.after(player_movement));
-
+            .add_systems(Update, rcs_particles.after(movement));
     }
 }
 
+fn nozzle_offset(facing: Facing) -> Vec2 {
+    facing.direction() * NOZZLE_EXTENTS
+}
+
 fn setup_particle_effect(
     mut commands: Commands<'_, '_>,
     player: Query<Entity, With<PlayerTag>>,
@@ -42,23 +51,22 @@ fn setup_particle_effect(
 
     let init_age = SetAttributeModifier::new(Attribute::AGE, age);
 
-    let lifetime = writer.lit(0.2).expr();
+    let lifetime = writer.lit(0.3).expr();
     let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
 
     let init_pos = SetPositionCircleModifier {
-        // center: (writer.lit(-1.0) * parent_velocity.clone()).expr(),
         center: writer.lit(Vec3::ZERO).expr(),
         axis: writer.lit(Vec3::Z).expr(),
-        radius: writer.lit(0.1).expr(),
+        radius: writer.lit(1.0).expr(),
         dimension: ShapeDimension::Volume,
     };
 
-    let drag = writer.lit(2.).expr();
+    let drag = writer.lit(4.).expr();
     let update_drag = LinearDragModifier::new(drag);
 
     let ortho = writer.lit(Vec3::Z).cross(direction.clone());
     let spread = writer.rand(ScalarType::Float) * writer.lit(2.) - writer.lit(1.);
-    let speed = writer.lit(3.0);
+    let speed = writer.lit(GAS_SPEED);
     let velocity =
         parent_velocity + (direction + ortho * spread * writer.lit(0.3)).normalized() * speed;
     let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, (velocity).expr());
@@ -77,71 +85,71 @@ fn setup_particle_effect(
                 sample_mapping: ImageSampleMapping::ModulateOpacityFromR,
             })
             .render(SetSizeModifier {
-                size: Vec2::splat(15.0).into(),
-                screen_space_size: true,
+                size: Vec2::splat(6.0).into(),
+                screen_space_size: false,
             })
             .render(ColorOverLifetimeModifier { gradient }),
     );
 
-    commands.entity(player.single()).with_children(|player| {
-        player
-            .spawn(ParticleEffectBundle::new(effect.clone()).with_spawner(spawner))
-            .insert(TransformBundle::from_transform(Transform::from_xyz(
-                -310., -250., 0.,
-            )))
-            .insert(RightRCS);
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    commands.entity(player).with_children(|player| {
         player
             .spawn(ParticleEffectBundle::new(effect).with_spawner(spawner))
-            .insert(TransformBundle::from_transform(Transform::from_xyz(
-                310., -250., 0.,
-            )))
-            .insert(LeftRCS);
+            .insert(RcsEmitter::default())
+            .insert(Name::new("RCS"));
     });
 }
 
+/// Emits gas opposite to the impulse applied by `movement`, proportionally to its strength.
 fn rcs_particles(
-    player: Query<(&Velocity, &ExternalImpulse, &GlobalTransform), With<PlayerTag>>,
-    mut right_rcs: Query<(&mut CompiledParticleEffect, &mut EffectSpawner), With<RightRCS>>,
-    mut left_rcs: Query<(&mut CompiledParticleEffect, &mut EffectSpawner), Without<RightRCS>>,
+    time: Res<Time>,
+    model: Res<PlayerMovementModel>,
+    models: Res<Assets<MovementModel>>,
+    player: Query<(&Velocity, &ExternalImpulse), With<PlayerTag>>,
+    mut emitters: Query<(
+        &mut RcsEmitter,
+        &mut Transform,
+        &mut CompiledParticleEffect,
+        &mut EffectSpawner,
+    )>,
 ) {
-    let Ok((velocity, impulse, transform)) = player.get_single() else {
+    let Ok((velocity, impulse)) = player.get_single() else {
         return;
     };
 
-    let Ok((mut right_effect, mut right_spawner)) = right_rcs.get_single_mut() else {
+    let Some(model) = models.get(&model.0) else {
         return;
     };
 
-    let Ok((mut left_effect, mut left_spawner)) = left_rcs.get_single_mut() else {
-        return;
+    let full_thrust = model.thrust * time.delta_seconds();
+    let intensity = if full_thrust > 0.0 {
+        (impulse.impulse.length() / full_thrust).min(1.0)
+    } else {
+        0.0
     };
 
-    if impulse.impulse.length_squared() > 0.0 {
-        let dir = -impulse.impulse.normalize();
-        set_effect(&mut right_effect, &mut right_spawner, velocity, dir);
-        set_effect(&mut left_effect, &mut left_spawner, velocity, dir);
-    } else if impulse.torque_impulse.abs() > 0.0 {
-        let dir = if impulse.torque_impulse > 0.0 {
-            transform.up().truncate()
-        } else {
-            -transform.up().truncate()
-        };
-
-        set_effect(&mut right_effect, &mut right_spawner, velocity, dir);
-        set_effect(&mut left_effect, &mut left_spawner, velocity, -dir);
-    } else {
-        right_spawner.set_active(false);
-        left_spawner.set_active(false);
+    for (mut emitter, mut transform, mut effect, mut spawner) in emitters.iter_mut() {
+        if intensity <= 0.0 {
+            emitter.emission = 0.0;
+            spawner.set_active(false);
+            continue;
+        }
+
+        let direction = -impulse.impulse.normalize();
+        let offset = nozzle_offset(Facing::from_direction(direction));
+        transform.translation = offset.extend(transform.translation.z);
+
+        emitter.emission += intensity;
+        let active = emitter.emission >= 1.0;
+        if active {
+            emitter.emission -= 1.0;
+        }
+
+        spawner.set_active(active);
+        effect.set_property("parent_velocity", velocity.linvel.extend(0.).into());
+        effect.set_property("direction", direction.extend(0.).into());
     }
 }
-
-fn set_effect(
-    effect: &mut CompiledParticleEffect,
-    spawner: &mut EffectSpawner,
-    velocity: &Velocity,
-    dir: Vec2,
-) {
-    spawner.set_active(true);
-    effect.set_property("parent_velocity", velocity.linvel.extend(0.).into());
-    effect.set_property("direction", Vec3::new(0., dir.x, dir.y).into());
-}
@@ -1,6 +1,23 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::animation::asset::AtlasLayoutAsset;
+use crate::animation::SpriteAnimator;
+use crate::audio::{Bus, Sound, SoundEmitter};
+use crate::health::Health;
+use crate::inventory::Inventory;
+use crate::lighting::Flashlight;
+use crate::suit::Suit;
+
+use self::animation::PlayerAnimation;
+use self::movement::Thrusters;
+
+pub mod animation;
+pub mod movement;
+
+// Health of the player when full
+pub const PLAYER_HEALTH: f32 = 100.0;
+
 #[derive(Component)]
 pub struct PlayerTag;
 
@@ -9,6 +26,14 @@ pub struct PlayerBundle {
     tag: PlayerTag,
     name: Name,
     sprite: SpriteSheetBundle,
+    animation: PlayerAnimation,
+    animator: SpriteAnimator,
+    thrusters: Thrusters,
+    thruster_sound: SoundEmitter,
+    flashlight: Flashlight,
+    inventory: Inventory,
+    health: Health,
+    suit: Suit,
     rigidbody: RigidBody,
     friction: Friction,
     velocity: Velocity,
@@ -19,7 +44,11 @@ pub struct PlayerBundle {
 }
 
 impl PlayerBundle {
-    pub fn new(transform: Transform, image: Handle<TextureAtlas>) -> Self {
+    pub fn new(
+        transform: Transform,
+        layout: Handle<AtlasLayoutAsset>,
+        image: Handle<TextureAtlas>,
+    ) -> Self {
         let sprite = SpriteSheetBundle {
             transform,
             texture_atlas: image,
@@ -36,6 +65,16 @@ impl PlayerBundle {
             tag: PlayerTag,
             name: Name::new("Player"),
             sprite,
+            animation: PlayerAnimation::new(layout),
+            animator: SpriteAnimator::default(),
+            thrusters: Thrusters::default(),
+            thruster_sound: SoundEmitter::new(Sound::ThrusterHiss, Bus::Sfx)
+                .with_volume(0.3)
+                .with_gain(0.0),
+            flashlight: Flashlight::default(),
+            inventory: Inventory::default(),
+            health: Health::new(PLAYER_HEALTH),
+            suit: Suit::new(100.0),
             rigidbody: RigidBody::Dynamic,
             friction: Friction::coefficient(0.0),
             force: ExternalImpulse::default(),
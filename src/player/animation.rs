@@ -0,0 +1,130 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::actions::ActionState;
+use crate::animation::asset::AtlasLayoutAsset;
+use crate::animation::SpriteAnimator;
+use crate::AppState;
+
+const DRIFT_SPEED: f32 = 5.0;
+
+pub struct PlayerAnimationPlugin;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    #[default]
+    Down,
+    DownRight,
+    Right,
+    UpRight,
+    Up,
+    UpLeft,
+    Left,
+    DownLeft,
+}
+
+impl Facing {
+    // Counterclockwise, starting from the x axis
+    const SECTORS: [Facing; 8] = [
+        Facing::Right,
+        Facing::UpRight,
+        Facing::Up,
+        Facing::UpLeft,
+        Facing::Left,
+        Facing::DownLeft,
+        Facing::Down,
+        Facing::DownRight,
+    ];
+
+    pub fn from_direction(direction: Vec2) -> Self {
+        let sector = (direction.y.atan2(direction.x) / FRAC_PI_4).round() as i32;
+        Self::SECTORS[sector.rem_euclid(8) as usize]
+    }
+
+    /// Unit vector pointing towards the facing direction.
+    pub fn direction(&self) -> Vec2 {
+        let sector = Self::SECTORS
+            .iter()
+            .position(|facing| facing == self)
+            .unwrap_or(0);
+        Vec2::from_angle(sector as f32 * FRAC_PI_4)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Facing::Down => "down",
+            Facing::DownRight => "down_right",
+            Facing::Right => "right",
+            Facing::UpRight => "up_right",
+            Facing::Up => "up",
+            Facing::UpLeft => "up_left",
+            Facing::Left => "left",
+            Facing::DownLeft => "down_left",
+        }
+    }
+}
+
+/// Chooses the clip of the player's `SpriteAnimator` from its facing and movement.
+#[derive(Debug, Component)]
+pub struct PlayerAnimation {
+    pub layout: Handle<AtlasLayoutAsset>,
+    pub facing: Facing,
+}
+
+impl PlayerAnimation {
+    pub fn new(layout: Handle<AtlasLayoutAsset>) -> Self {
+        Self {
+            layout,
+            facing: Facing::default(),
+        }
+    }
+}
+
+impl Plugin for PlayerAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        // Cutscenes play their own animations on the player
+        app.add_systems(
+            Update,
+            animate_player.run_if(not(in_state(AppState::Cutscene))),
+        );
+    }
+}
+
+fn animate_player(
+    actions: Res<ActionState>,
+    state: Res<State<AppState>>,
+    layouts: Res<Assets<AtlasLayoutAsset>>,
+    mut player: Query<(&mut PlayerAnimation, &mut SpriteAnimator, &Velocity)>,
+) {
+    for (mut animation, mut animator, velocity) in player.iter_mut() {
+        let thrust = if *state.get() == AppState::InGame {
+            actions.thrust()
+        } else {
+            Vec2::ZERO
+        };
+
+        let pose = if thrust != Vec2::ZERO {
+            animation.facing = Facing::from_direction(thrust);
+            "thrust"
+        } else if velocity.linvel.length() > DRIFT_SPEED {
+            "drift"
+        } else {
+            "idle"
+        };
+
+        let Some(layout) = layouts.get(&animation.layout) else {
+            continue;
+        };
+
+        // Clips that are not drawn yet fall back to the idle pose
+        let facing = animation.facing.name();
+        if let Some(clip) = [format!("{pose}_{facing}"), format!("idle_{facing}")]
+            .iter()
+            .find_map(|name| layout.clips.get(name))
+        {
+            animator.play(clip);
+        }
+    }
+}
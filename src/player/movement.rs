@@ -0,0 +1,229 @@
+use bevy::asset::{AssetLoader, AsyncReadExt};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use thiserror::*;
+
+use crate::actions::{Action, ActionState};
+use crate::player::PlayerTag;
+use crate::{AppState, ResetEvent};
+
+pub struct MovementPlugin;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum MovementMode {
+    /// Damped movement, the player stops by itself like when walking on ground.
+    #[default]
+    Damped,
+    /// No damping at all, the player has to brake with the RCS.
+    Inertial,
+    /// No damping, but the RCS automatically brakes when no thrust is requested.
+    Assisted,
+}
+
+impl MovementMode {
+    fn next(self) -> Self {
+        match self {
+            MovementMode::Damped => MovementMode::Inertial,
+            MovementMode::Inertial => MovementMode::Assisted,
+            MovementMode::Assisted => MovementMode::Damped,
+        }
+    }
+}
+
+/// Tuning values of the player movement, read from `player.movement.ron`.
+#[derive(Debug, Clone, Deserialize, TypePath, Asset)]
+pub struct MovementModel {
+    pub mode: MovementMode,
+    pub thrust: f32,
+    pub walk_damping: f32,
+    pub brake: f32,
+    pub stop_speed: f32,
+    pub fuel_capacity: f32,
+    pub fuel_consumption: f32,
+    pub fuel_recharge: f32,
+}
+
+#[derive(Debug, Error)]
+pub enum MovementModelLoadError {
+    #[error("Could not load movement model: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+}
+
+#[derive(Debug, Default)]
+pub struct MovementModelLoader;
+
+impl AssetLoader for MovementModelLoader {
+    type Asset = MovementModel;
+
+    type Settings = ();
+
+    type Error = MovementModelLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await?;
+            Ok(ron::de::from_bytes(&buf)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["movement.ron"]
+    }
+}
+
+#[derive(Debug, Resource)]
+pub struct PlayerMovementModel(pub Handle<MovementModel>);
+
+impl FromWorld for PlayerMovementModel {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load("player.movement.ron"))
+    }
+}
+
+#[derive(Debug, Default, Component)]
+pub struct Thrusters {
+    pub mode: MovementMode,
+    pub fuel: f32,
+    /// How hard the thrusters burned during the last frame, from 0.0 to 1.0.
+    pub burn: f32,
+}
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<MovementModel>()
+            .init_asset_loader::<MovementModelLoader>()
+            .init_resource::<PlayerMovementModel>()
+            .add_systems(
+                Update,
+                (
+                    refuel,
+                    (switch_movement_mode, movement).run_if(in_state(AppState::InGame)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Fills the tank and applies the default mode when the model is (re)loaded or the player resets.
+fn refuel(
+    mut asset_events: EventReader<AssetEvent<MovementModel>>,
+    mut reset_events: EventReader<ResetEvent>,
+    model: Res<PlayerMovementModel>,
+    models: Res<Assets<MovementModel>>,
+    mut thrusters: Query<&mut Thrusters>,
+) {
+    let model_changed = asset_events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&model.0) || event.is_modified(&model.0));
+    let reset = reset_events.read().count() > 0;
+
+    if !model_changed && !reset {
+        return;
+    }
+
+    let Some(model) = models.get(&model.0) else {
+        return;
+    };
+
+    for mut thrusters in thrusters.iter_mut() {
+        if model_changed {
+            thrusters.mode = model.mode;
+        }
+        thrusters.fuel = model.fuel_capacity;
+    }
+}
+
+fn switch_movement_mode(actions: Res<ActionState>, mut thrusters: Query<&mut Thrusters>) {
+    if actions.just_pressed(Action::SwitchMovementMode) {
+        for mut thrusters in thrusters.iter_mut() {
+            thrusters.mode = thrusters.mode.next();
+            info!("Movement mode: {:?}", thrusters.mode);
+        }
+    }
+}
+
+/// Fires the RCS against the current velocity, returns whether it was needed.
+fn brake(
+    velocity: &mut Velocity,
+    impulse: &mut ExternalImpulse,
+    model: &MovementModel,
+    dt: f32,
+) -> bool {
+    if velocity.linvel.length() > model.stop_speed {
+        impulse.impulse -= velocity.linvel.normalize() * model.brake * dt;
+        true
+    } else {
+        if velocity.linvel != Vec2::ZERO {
+            velocity.linvel = Vec2::ZERO;
+        }
+        false
+    }
+}
+
+pub fn movement(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    model: Res<PlayerMovementModel>,
+    models: Res<Assets<MovementModel>>,
+    mut player: Query<
+        (
+            &mut ExternalImpulse,
+            &mut Velocity,
+            &mut Damping,
+            &mut Thrusters,
+        ),
+        With<PlayerTag>,
+    >,
+) {
+    let Some(model) = models.get(&model.0) else {
+        return;
+    };
+
+    let (mut impulse, mut velocity, mut damping, mut thrusters) = player.single_mut();
+    let dt = time.delta_seconds();
+
+    let linear_damping = match thrusters.mode {
+        MovementMode::Damped => model.walk_damping,
+        MovementMode::Inertial | MovementMode::Assisted => 0.0,
+    };
+    if damping.linear_damping != linear_damping {
+        damping.linear_damping = linear_damping;
+    }
+
+    let thrust = if thrusters.fuel > 0.0 {
+        actions.thrust()
+    } else {
+        Vec2::ZERO
+    };
+
+    let braking = thrusters.fuel > 0.0
+        && thrust == Vec2::ZERO
+        && match thrusters.mode {
+            MovementMode::Damped => false,
+            MovementMode::Inertial => actions.pressed(Action::Brake),
+            MovementMode::Assisted => true,
+        };
+
+    let mut burn = thrust.length();
+    impulse.impulse += thrust * model.thrust * dt;
+
+    if braking && brake(&mut velocity, &mut impulse, model, dt) {
+        burn = 1.0;
+    }
+
+    thrusters.burn = burn;
+    thrusters.fuel = if burn > 0.0 {
+        (thrusters.fuel - burn * model.fuel_consumption * dt).max(0.0)
+    } else {
+        (thrusters.fuel + model.fuel_recharge * dt).min(model.fuel_capacity)
+    };
+}
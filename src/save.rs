@@ -0,0 +1,314 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::actions::{Action, ActionState};
+use crate::camera::effects::CameraEffect;
+use crate::checkpoint::LastCheckpoint;
+use crate::dialog::{CurrentDialog, Dialog};
+use crate::health::{Dying, Health};
+use crate::inventory::Inventory;
+use crate::level::{CurrentLevel, Level};
+use crate::objects::FiredTriggers;
+use crate::player::{PlayerTag, PLAYER_HEALTH};
+use crate::storage::data_dir;
+use crate::story::StoryFlags;
+use crate::suit::Suit;
+use crate::AppState;
+
+const SAVE_VERSION: u32 = 3;
+pub const QUICK_SAVE_SLOT: usize = 0;
+
+pub struct SavePlugin;
+
+#[derive(Debug, Event)]
+pub struct SaveEvent(pub usize);
+
+#[derive(Debug, Event)]
+pub struct LoadEvent(pub usize);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub level: Level,
+    pub player_translation: Vec3,
+    pub player_velocity: Vec2,
+    pub fired_triggers: Vec<Dialog>,
+    pub story_flags: StoryFlags,
+    pub dialog: Option<(Dialog, usize)>,
+    pub inventory: Inventory,
+    pub player_health: f32,
+}
+
+/// Saves written before the health of the player was saved, it is restored fully.
+#[derive(Debug, Deserialize)]
+struct SaveDataV2 {
+    level: Level,
+    player_translation: Vec3,
+    player_velocity: Vec2,
+    fired_triggers: Vec<Dialog>,
+    story_flags: StoryFlags,
+    dialog: Option<(Dialog, usize)>,
+    inventory: Inventory,
+}
+
+impl From<SaveDataV2> for SaveData {
+    fn from(data: SaveDataV2) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            level: data.level,
+            player_translation: data.player_translation,
+            player_velocity: data.player_velocity,
+            fired_triggers: data.fired_triggers,
+            story_flags: data.story_flags,
+            dialog: data.dialog,
+            inventory: data.inventory,
+            player_health: PLAYER_HEALTH,
+        }
+    }
+}
+
+/// Saves written before the inventory existed.
+#[derive(Debug, Deserialize)]
+struct SaveDataV1 {
+    level: Level,
+    player_translation: Vec3,
+    player_velocity: Vec2,
+    fired_triggers: Vec<Dialog>,
+    story_flags: StoryFlags,
+    dialog: Option<(Dialog, usize)>,
+}
+
+impl From<SaveDataV1> for SaveDataV2 {
+    fn from(data: SaveDataV1) -> Self {
+        Self {
+            level: data.level,
+            player_translation: data.player_translation,
+            player_velocity: data.player_velocity,
+            fired_triggers: data.fired_triggers,
+            story_flags: data.story_flags,
+            dialog: data.dialog,
+            inventory: Inventory::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not access save file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+    #[error("Could not write ron: {0}")]
+    Writing(#[from] ron::Error),
+    #[error("Unknown save version: {0}")]
+    UnknownVersion(u32),
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveEvent>()
+            .add_event::<LoadEvent>()
+            .add_systems(
+                Update,
+                (
+                    quick_save_input
+                        .run_if(in_state(AppState::InGame).or_else(in_state(AppState::InDialog))),
+                    save_game,
+                    load_game,
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    data_dir().join("saves").join(format!("slot{slot}.ron"))
+}
+
+pub fn slot_exists(slot: usize) -> bool {
+    slot_path(slot).exists()
+}
+
+fn write_save(slot: usize, data: &SaveData) -> Result<(), SaveError> {
+    let path = slot_path(slot);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        path,
+        ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())?,
+    )?;
+    Ok(())
+}
+
+fn read_save(slot: usize) -> Result<SaveData, SaveError> {
+    let text = fs::read_to_string(slot_path(slot))?;
+    let header: SaveHeader = ron::de::from_str(&text)?;
+
+    // When the format changes, the previous `SaveData` is kept as `SaveDataV<n>` and migrated
+    // here to the current version.
+    match header.version {
+        SAVE_VERSION => Ok(ron::de::from_str(&text)?),
+        2 => Ok(ron::de::from_str::<SaveDataV2>(&text)?.into()),
+        1 => Ok(SaveDataV2::from(ron::de::from_str::<SaveDataV1>(&text)?).into()),
+        version => Err(SaveError::UnknownVersion(version)),
+    }
+}
+
+fn quick_save_input(
+    actions: Res<ActionState>,
+    mut save_events: EventWriter<SaveEvent>,
+    mut load_events: EventWriter<LoadEvent>,
+) {
+    if actions.just_pressed(Action::QuickSave) {
+        save_events.send(SaveEvent(QUICK_SAVE_SLOT));
+    }
+
+    if actions.just_pressed(Action::QuickLoad) {
+        load_events.send(LoadEvent(QUICK_SAVE_SLOT));
+    }
+}
+
+type SavedPlayer<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Velocity,
+        &'static Inventory,
+        &'static Health,
+        Has<Dying>,
+    ),
+    With<PlayerTag>,
+>;
+
+fn save_game(
+    mut events: EventReader<SaveEvent>,
+    player: SavedPlayer,
+    level: Res<CurrentLevel>,
+    fired_triggers: Res<FiredTriggers>,
+    story_flags: Res<StoryFlags>,
+    current_dialog: Res<CurrentDialog>,
+    state: Res<State<AppState>>,
+) {
+    for SaveEvent(slot) in events.read() {
+        let Ok((transform, velocity, inventory, health, dying)) = player.get_single() else {
+            continue;
+        };
+        if dying {
+            info!("Not saving the game while the player is dying");
+            continue;
+        }
+
+        let data = SaveData {
+            version: SAVE_VERSION,
+            level: level.0,
+            player_translation: transform.translation,
+            player_velocity: velocity.linvel,
+            fired_triggers: fired_triggers.0.iter().copied().collect(),
+            story_flags: story_flags.clone(),
+            dialog: (*state.get() == AppState::InDialog)
+                .then(|| (current_dialog.dialog(), current_dialog.current_line())),
+            inventory: inventory.clone(),
+            player_health: health.current,
+        };
+
+        match write_save(*slot, &data) {
+            Ok(()) => info!("Game saved in slot {slot}"),
+            Err(err) => error!("Could not save the game in slot {slot}: {err}"),
+        }
+    }
+}
+
+type LoadedPlayer<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Has<Dying>,
+        &'static mut Transform,
+        &'static mut Velocity,
+        &'static mut Inventory,
+        &'static mut Health,
+        &'static mut Suit,
+    ),
+    With<PlayerTag>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn load_game(
+    mut commands: Commands,
+    mut events: EventReader<LoadEvent>,
+    mut player: LoadedPlayer,
+    mut level: ResMut<CurrentLevel>,
+    mut fired_triggers: ResMut<FiredTriggers>,
+    mut story_flags: ResMut<StoryFlags>,
+    mut current_dialog: ResMut<CurrentDialog>,
+    mut state: ResMut<NextState<AppState>>,
+    mut last_checkpoint: ResMut<LastCheckpoint>,
+    mut camera_effects: EventWriter<CameraEffect>,
+) {
+    for LoadEvent(slot) in events.read() {
+        let data = match read_save(*slot) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Could not load the game from slot {slot}: {err}");
+                continue;
+            }
+        };
+
+        let Ok((entity, dying, mut transform, mut velocity, mut inventory, mut health, mut suit)) =
+            player.get_single_mut()
+        else {
+            continue;
+        };
+
+        // A save loaded while the screen fades out on death must not respawn the player
+        if dying {
+            commands.entity(entity).remove::<Dying>();
+            camera_effects.send(CameraEffect::Fade {
+                to: 0.0,
+                duration: 0.0,
+            });
+        }
+
+        transform.translation = data.player_translation;
+        *velocity = Velocity::linear(data.player_velocity);
+        *inventory = data.inventory;
+        health.current = data.player_health.min(health.max);
+        suit.refill();
+
+        // Writing the level even if it did not change rebuilds the scene
+        level.0 = data.level;
+        fired_triggers.0 = data.fired_triggers.into_iter().collect();
+        *story_flags = data.story_flags;
+
+        // Dying or resetting goes back to the loaded state rather than to an older checkpoint
+        *last_checkpoint = LastCheckpoint {
+            name: None,
+            position: Some(transform.translation),
+            fired_triggers: fired_triggers.0.clone(),
+            story_flags: story_flags.clone(),
+            inventory: inventory.clone(),
+        };
+
+        match data.dialog {
+            Some((dialog, line)) => {
+                current_dialog.set(dialog);
+                current_dialog.set_line(line);
+                state.set(AppState::InDialog);
+            }
+            None => state.set(AppState::InGame),
+        }
+    }
+}
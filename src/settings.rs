@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use bevy_rapier2d::prelude::DebugRenderContext;
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::actions::ActionMap;
+use crate::audio::VolumeSettings;
+use crate::storage::config_dir;
+
+const SETTINGS_VERSION: u32 = 1;
+
+/// Holds the settings read before the app is built, the window being created from them.
+pub struct SettingsPlugin {
+    settings: Settings,
+    error: Option<SettingsError>,
+}
+
+/// Language of the texts of the game. Only English texts are written for now.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+}
+
+impl Language {
+    /// Languages whose texts are written, the menu only offers these.
+    pub const AVAILABLE: &'static [Language] = &[Language::English];
+
+    pub fn next(self) -> Self {
+        let index = Self::AVAILABLE
+            .iter()
+            .position(|language| *language == self)
+            .map_or(0, |index| (index + 1) % Self::AVAILABLE.len());
+        Self::AVAILABLE[index]
+    }
+}
+
+/// Everything the player can configure, saved in `settings.ron`. The other resources are built
+/// from it at startup and it follows them when they change.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub resolution: (f32, f32),
+    pub window_mode: WindowMode,
+    pub present_mode: PresentMode,
+    pub ui_scale: f64,
+    /// Size of a level pixel on the screen.
+    pub camera_scale: f32,
+    pub debug_render: bool,
+    pub language: Language,
+    pub volume: VolumeSettings,
+    pub bindings: ActionMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            resolution: (1280.0, 720.0),
+            window_mode: WindowMode::Windowed,
+            present_mode: PresentMode::AutoVsync,
+            ui_scale: 1.0,
+            camera_scale: 3.0,
+            debug_render: false,
+            language: Language::default(),
+            volume: VolumeSettings::default(),
+            bindings: ActionMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SettingsHeader {
+    version: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Could not access settings file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not parse ron: {0}")]
+    Parsing(#[from] ron::error::SpannedError),
+    #[error("Could not write ron: {0}")]
+    Writing(#[from] ron::Error),
+    #[error("Unknown settings version: {0}")]
+    UnknownVersion(u32),
+}
+
+fn settings_path() -> PathBuf {
+    config_dir().join("settings.ron")
+}
+
+fn read_settings() -> Result<Settings, SettingsError> {
+    let text = fs::read_to_string(settings_path())?;
+    let header: SettingsHeader = ron::de::from_str(&text)?;
+
+    // Missing fields take their default value, only incompatible changes need a new version
+    let mut settings: Settings = match header.version {
+        SETTINGS_VERSION => ron::de::from_str(&text)?,
+        version => return Err(SettingsError::UnknownVersion(version)),
+    };
+    settings.bindings.add_missing_defaults();
+    Ok(settings)
+}
+
+pub fn write_settings(settings: &Settings) -> Result<(), SettingsError> {
+    let path = settings_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        path,
+        ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())?,
+    )?;
+    Ok(())
+}
+
+impl SettingsPlugin {
+    /// Reads `settings.ron`, the defaults are used when it can't be read.
+    pub fn read() -> Self {
+        match read_settings() {
+            Ok(settings) => Self {
+                settings,
+                error: None,
+            },
+            Err(err) => Self {
+                settings: Settings::default(),
+                error: Some(err),
+            },
+        }
+    }
+
+    pub fn window(&self) -> Window {
+        let (width, height) = self.settings.resolution;
+        Window {
+            resolution: (width, height).into(),
+            mode: self.settings.window_mode,
+            present_mode: self.settings.present_mode,
+            ..Default::default()
+        }
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // Logged here as the log is only set up once the app is built
+        if let Some(err) = &self.error {
+            info!("Using default settings: {err}");
+        }
+        let settings = self.settings.clone();
+
+        app.insert_resource(settings.bindings.clone())
+            .insert_resource(settings.volume.clone())
+            .insert_resource(settings.language)
+            .insert_resource(UiScale(settings.ui_scale))
+            .insert_resource(settings)
+            .add_systems(Startup, apply_settings)
+            .add_systems(
+                Update,
+                apply_camera_scale.run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(Last, save_settings);
+    }
+}
+
+fn apply_settings(settings: Res<Settings>, mut debug_render_context: ResMut<DebugRenderContext>) {
+    debug_render_context.enabled = settings.debug_render;
+}
+
+fn apply_camera_scale(
+    settings: Res<Settings>,
+    mut cameras: Query<&mut OrthographicProjection, With<Camera2d>>,
+) {
+    for mut projection in cameras.iter_mut() {
+        projection.scaling_mode = ScalingMode::WindowSize(settings.camera_scale);
+    }
+}
+
+/// Saves the settings each time one of them is changed in game, either in the resources built
+/// from them or directly like the camera scale.
+fn save_settings(
+    mut settings: ResMut<Settings>,
+    windows: Query<Ref<Window>, With<PrimaryWindow>>,
+    ui_scale: Res<UiScale>,
+    debug_render_context: Res<DebugRenderContext>,
+    language: Res<Language>,
+    volume: Res<VolumeSettings>,
+    bindings: Res<ActionMap>,
+) {
+    let edited = settings.is_changed() && !settings.is_added();
+    let window = windows.get_single().ok();
+    let changed = edited
+        || window.as_ref().is_some_and(|window| window.is_changed())
+        || ui_scale.is_changed()
+        || debug_render_context.is_changed()
+        || language.is_changed()
+        || volume.is_changed()
+        || bindings.is_changed();
+    if !changed {
+        return;
+    }
+
+    let mut new_settings = Settings {
+        ui_scale: ui_scale.0,
+        debug_render: debug_render_context.enabled,
+        language: *language,
+        volume: volume.clone(),
+        bindings: bindings.clone(),
+        ..settings.clone()
+    };
+    if let Some(window) = window {
+        new_settings.resolution = (window.resolution.width(), window.resolution.height());
+        new_settings.window_mode = window.mode;
+        new_settings.present_mode = window.present_mode;
+    }
+
+    // The window changes on its own all the time, only actual differences are saved
+    if edited || new_settings != *settings {
+        if let Err(err) = write_settings(&new_settings) {
+            error!("Could not save settings: {err}");
+        }
+        // Catching up with the other resources is not an edit
+        *settings.bypass_change_detection() = new_settings;
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+const GAME_DIRECTORY: &str = "the_lost_vessel";
+
+/// Directory where the game stores its saves, following the platform conventions.
+pub fn data_dir() -> PathBuf {
+    platform_dir("XDG_DATA_HOME", ".local/share").join(GAME_DIRECTORY)
+}
+
+/// Directory where the game stores its configuration, following the platform conventions.
+pub fn config_dir() -> PathBuf {
+    platform_dir("XDG_CONFIG_HOME", ".config").join(GAME_DIRECTORY)
+}
+
+fn platform_dir(xdg_var: &str, xdg_fallback: &str) -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        std::env::var_os(xdg_var)
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(xdg_fallback)))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from("."))
+}
@@ -0,0 +1,21 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+pub struct StoryFlags(BTreeSet<String>);
+
+impl StoryFlags {
+    pub fn set(&mut self, flag: &str) {
+        self.0.insert(flag.to_string());
+    }
+
+    pub fn unset(&mut self, flag: &str) {
+        self.0.remove(flag);
+    }
+
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.0.contains(flag)
+    }
+}
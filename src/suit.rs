@@ -0,0 +1,364 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::{Bus, Sound, SoundEmitter};
+use crate::companion::Companion;
+use crate::dialog::bubble::ShowBubble;
+use crate::dialog::Dialog;
+use crate::health::{DamageEvent, Health};
+use crate::level::asset::LevelAsset;
+use crate::level::{CurrentLevelAsset, LevelEntity, SpawnLevelEvent, SpawnLevelSet};
+use crate::lighting::{LightAnimation, PointLight2d};
+use crate::machinery::{spawn_machinery, MachinerySet, PowerNode};
+use crate::player::movement::Thrusters;
+use crate::player::PlayerTag;
+use crate::{AppState, ResetEvent};
+
+// Energy used per second by the life support, and more while the thrusters burn at full power
+const IDLE_DRAIN: f32 = 0.5;
+const THRUST_DRAIN: f32 = 3.0;
+const RECHARGE_RATE: f32 = 25.0;
+// Health lost per second once the suit is empty
+const SUFFOCATION_DAMAGE: f32 = 5.0;
+// Fractions of the energy under which the AI warns the player, and the dialog it says
+const WARNINGS: &[(f32, Dialog)] = &[
+    (0.5, Dialog::WarningSuitHalf),
+    (0.25, Dialog::WarningSuitLow),
+    (0.0, Dialog::WarningSuitEmpty),
+];
+// A warning can be said again once the energy went back this much above its threshold
+const WARNING_REARM: f32 = 0.1;
+const STATION_SOUND_RANGE: f32 = 100.0;
+const BAR_WIDTH: f32 = 200.0;
+
+pub struct SuitPlugin;
+
+/// The energy of the suit of the player, keeping it alive and feeding the thrusters.
+#[derive(Debug, Clone, Component)]
+pub struct Suit {
+    pub energy: f32,
+    pub max: f32,
+    /// Index in `WARNINGS` of the last warning said.
+    warned: Option<usize>,
+}
+
+impl Suit {
+    pub fn new(max: f32) -> Self {
+        Self {
+            energy: max,
+            max,
+            warned: None,
+        }
+    }
+
+    pub fn refill(&mut self) {
+        self.energy = self.max;
+        self.warned = None;
+    }
+
+    fn fraction(&self) -> f32 {
+        self.energy / self.max.max(0.001)
+    }
+}
+
+/// Refills the suit of the player standing at it while it is powered.
+#[derive(Debug, Component)]
+pub struct RechargeStation {
+    pub charging: bool,
+}
+
+#[derive(Debug, Component)]
+struct HudUI;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum HudBar {
+    Health,
+    Suit,
+}
+
+impl Plugin for SuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_hud)
+            .add_systems(
+                Update,
+                spawn_recharge_stations
+                    .after(SpawnLevelSet)
+                    .before(spawn_machinery),
+            )
+            .add_systems(
+                Update,
+                (
+                    (drain_suit, recharge_suit, suit_warnings)
+                        .chain()
+                        .run_if(in_state(AppState::InGame)),
+                    refill_on_reset,
+                )
+                    .chain()
+                    .after(MachinerySet),
+            )
+            .add_systems(Update, (show_hud, update_hud_bars));
+    }
+}
+
+/// Spawns the recharge stations of the current level each time it is built. They are power nodes
+/// and can be wired to the machinery.
+fn spawn_recharge_stations(
+    mut commands: Commands,
+    mut events: EventReader<SpawnLevelEvent>,
+    level_asset: Res<CurrentLevelAsset>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = levels.get(&level_asset.0) else {
+        return;
+    };
+
+    for spec in level.recharge_stations.iter() {
+        commands.spawn((
+            Name::new(spec.name.clone()),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.2, 0.5, 0.9),
+                    custom_size: Some(Vec2::new(10.0, 14.0)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(spec.position.0, spec.position.1, 0.1),
+                ..Default::default()
+            },
+            PointLight2d {
+                color: Color::rgb(0.3, 0.6, 1.0),
+                radius: 28.0,
+                intensity: 0.5,
+                animation: LightAnimation::Pulse {
+                    period: 1.0,
+                    amount: 0.4,
+                },
+                ..Default::default()
+            },
+            Collider::ball(14.0),
+            Sensor,
+            RechargeStation { charging: false },
+            SoundEmitter::new(Sound::ForceFieldHum, Bus::Sfx)
+                .with_range(STATION_SOUND_RANGE)
+                .with_volume(0.4)
+                .with_gain(0.0),
+            PowerNode::new(spec.name.clone()),
+            LevelEntity,
+        ));
+    }
+}
+
+/// The life support always draws some energy, the thrusters more. Without energy the player
+/// suffocates.
+fn drain_suit(
+    time: Res<Time>,
+    mut player: Query<(&mut Suit, &Thrusters), With<PlayerTag>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (mut suit, thrusters) in player.iter_mut() {
+        if suit.energy <= 0.0 {
+            damage.send(DamageEvent {
+                amount: SUFFOCATION_DAMAGE * dt,
+            });
+            continue;
+        }
+
+        let drain = IDLE_DRAIN + THRUST_DRAIN * thrusters.burn;
+        suit.energy = (suit.energy - drain * dt).max(0.0);
+    }
+}
+
+fn recharge_suit(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut stations: Query<(Entity, &mut RechargeStation, &PowerNode, &mut SoundEmitter)>,
+    mut player: Query<(Entity, &mut Suit), With<PlayerTag>>,
+    parents: Query<&Parent>,
+) {
+    let Ok((player, mut suit)) = player.get_single_mut() else {
+        return;
+    };
+
+    let is_player = |entity: Entity| {
+        entity == player
+            || parents
+                .get(entity)
+                .is_ok_and(|parent| parent.get() == player)
+    };
+
+    for (entity, mut station, node, mut sound) in stations.iter_mut() {
+        let touching =
+            rapier_context
+                .intersections_with(entity)
+                .any(|(entity1, entity2, intersecting)| {
+                    intersecting && (is_player(entity1) || is_player(entity2))
+                });
+        station.charging = node.powered && touching && suit.energy < suit.max;
+        sound.gain = if station.charging { 1.0 } else { 0.0 };
+
+        if station.charging {
+            suit.energy = (suit.energy + RECHARGE_RATE * time.delta_seconds()).min(suit.max);
+        }
+    }
+}
+
+/// The AI warns the player each time the energy goes under one of the `WARNINGS` thresholds.
+fn suit_warnings(
+    mut player: Query<(Entity, &mut Suit), With<PlayerTag>>,
+    companion: Query<Entity, With<Companion>>,
+    mut bubbles: EventWriter<ShowBubble>,
+) {
+    let Ok((player, mut suit)) = player.get_single_mut() else {
+        return;
+    };
+
+    let fraction = suit.fraction();
+    if let Some(warned) = suit.warned {
+        if fraction > WARNINGS[warned].0 + WARNING_REARM {
+            suit.warned = warned.checked_sub(1);
+        }
+    }
+
+    let next = suit.warned.map_or(0, |warned| warned + 1);
+    let Some(&(threshold, dialog)) = WARNINGS.get(next) else {
+        return;
+    };
+    if fraction <= threshold {
+        suit.warned = Some(next);
+        bubbles.send(ShowBubble {
+            speaker: companion.get_single().unwrap_or(player),
+            dialog,
+        });
+    }
+}
+
+fn refill_on_reset(mut events: EventReader<ResetEvent>, mut suits: Query<&mut Suit>) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    for mut suit in suits.iter_mut() {
+        suit.refill();
+    }
+}
+
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                visibility: Visibility::Hidden,
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            HudUI,
+        ))
+        .with_children(|commands| {
+            for (bar, label) in [(HudBar::Health, "Health"), (HudBar::Suit, "Suit")] {
+                commands
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle {
+                            style: Style {
+                                width: Val::Px(70.0),
+                                ..Default::default()
+                            },
+                            text: Text::from_section(
+                                label,
+                                TextStyle {
+                                    font_size: 20.0,
+                                    ..Default::default()
+                                },
+                            ),
+                            ..Default::default()
+                        });
+                        commands
+                            .spawn(NodeBundle {
+                                background_color: Color::rgba(0.2, 0.2, 0.2, 0.8).into(),
+                                style: Style {
+                                    width: Val::Px(BAR_WIDTH),
+                                    height: Val::Px(12.0),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            })
+                            .with_children(|commands| {
+                                commands.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Percent(100.0),
+                                            height: Val::Percent(100.0),
+                                            ..Default::default()
+                                        },
+                                        ..Default::default()
+                                    },
+                                    bar,
+                                ));
+                            });
+                    });
+            }
+        });
+}
+
+/// The HUD is shown in game and during dialogs, cutscenes and menus hide it.
+fn show_hud(state: Res<State<AppState>>, mut hud: Query<&mut Visibility, With<HudUI>>) {
+    if !state.is_changed() {
+        return;
+    }
+
+    for mut visibility in hud.iter_mut() {
+        *visibility = match state.get() {
+            AppState::InGame | AppState::InDialog => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_hud_bars(
+    player: Query<(&Health, &Suit), With<PlayerTag>>,
+    mut bars: Query<(&HudBar, &mut Style, &mut BackgroundColor)>,
+) {
+    let Ok((health, suit)) = player.get_single() else {
+        return;
+    };
+
+    for (bar, mut style, mut color) in bars.iter_mut() {
+        let (fraction, full_color) = match bar {
+            HudBar::Health => (
+                health.current / health.max.max(0.001),
+                Color::rgb(0.8, 0.2, 0.2),
+            ),
+            HudBar::Suit => (suit.fraction(), Color::rgb(0.2, 0.6, 1.0)),
+        };
+        let width = Val::Percent(fraction.clamp(0.0, 1.0) * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+        // The bars turn orange once they run low
+        let low_color = if fraction < 0.25 {
+            Color::rgb(1.0, 0.6, 0.1)
+        } else {
+            full_color
+        };
+        if color.0 != low_color {
+            color.0 = low_color;
+        }
+    }
+}
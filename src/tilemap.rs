@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::animation::SpriteAnimator;
+use crate::level::LevelEntity;
+use crate::lighting::{LightAnimation, PointLight2d};
+
 const MAP: &str = r#"
 999999999
 999999999
@@ -14,6 +18,8 @@ const MAP: &str = r#"
 
 const TILE_SIZE: f32 = 64.0;
 
+const WALL_LIGHT_TILE: i64 = 1;
+
 pub fn spawn_map(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -28,32 +34,33 @@ pub fn spawn_map(
         None,
         None,
     ));
+    let wall_light = asset_server.load("animations/wall_light.anim.ron");
 
     commands
-        .spawn((SpatialBundle::default(), Name::new("Map")))
+        .spawn((SpatialBundle::default(), Name::new("Map"), LevelEntity))
         .with_children(|commands| {
-            for (row, (col, tile)) in MAP
-                .lines()
-                .enumerate()
-                .flat_map(|(row, line)| std::iter::repeat(row).zip(line.bytes().enumerate()))
-            {
-                let tile_byte = &[tile];
-                let s = std::str::from_utf8(tile_byte).unwrap();
-                let index = i64::from_str_radix(s, 16).unwrap();
-
+            for (row, col, index) in tiles() {
                 let mut entity = commands.spawn(SpriteSheetBundle {
                     texture_atlas: atlas.clone(),
                     sprite: TextureAtlasSprite::new(index as usize),
-                    transform: Transform {
-                        translation: Vec3 {
-                            x: col as f32 * TILE_SIZE,
-                            y: -(row as f32 * TILE_SIZE),
-                            z: 0.0,
-                        },
-                        ..Default::default()
-                    },
+                    transform: Transform::from_translation(tile_position(row, col).extend(0.0)),
                     ..Default::default()
                 });
+                if index == WALL_LIGHT_TILE {
+                    entity.insert((
+                        SpriteAnimator::new(wall_light.clone()).with_offset(col as f32 * 0.7),
+                        PointLight2d {
+                            color: Color::rgb(1.0, 0.85, 0.6),
+                            radius: 96.0,
+                            intensity: 0.8,
+                            animation: LightAnimation::Flicker {
+                                speed: 3.0,
+                                amount: 0.3,
+                            },
+                            phase: col as f32,
+                        },
+                    ));
+                }
                 let collider_spec = get_collider(index);
                 if !collider_spec.is_empty() {
                     entity.insert(RigidBody::Fixed).with_children(|commands| {
@@ -71,37 +78,70 @@ pub fn spawn_map(
         });
 }
 
-pub fn get_collider(index: i64) -> Vec<(Vec2, Collider)> {
+/// Row, column and atlas index of every tile of the map.
+fn tiles() -> impl Iterator<Item = (usize, usize, i64)> {
+    MAP.lines().enumerate().flat_map(|(row, line)| {
+        line.bytes().enumerate().map(move |(col, tile)| {
+            let tile_byte = &[tile];
+            let s = std::str::from_utf8(tile_byte).unwrap();
+            (row, col, i64::from_str_radix(s, 16).unwrap())
+        })
+    })
+}
+
+fn tile_position(row: usize, col: usize) -> Vec2 {
+    Vec2::new(col as f32 * TILE_SIZE, -(row as f32 * TILE_SIZE))
+}
+
+/// Area covered by the tiles of the map, in world coordinates.
+pub fn map_bounds() -> Rect {
+    let rows = || MAP.lines().enumerate().filter(|(_, line)| !line.is_empty());
+    let first_row = rows().map(|(row, _)| row).min().unwrap_or_default();
+    let last_row = rows().map(|(row, _)| row).max().unwrap_or_default();
+    let cols = rows().map(|(_, line)| line.len()).max().unwrap_or_default();
+    let half_tile = TILE_SIZE / 2.0;
+    Rect::new(
+        -half_tile,
+        -(last_row as f32 * TILE_SIZE) - half_tile,
+        cols as f32 * TILE_SIZE - half_tile,
+        -(first_row as f32 * TILE_SIZE) + half_tile,
+    )
+}
+
+// Offset from the center of the tile and half size of each wall of a tile
+fn wall_boxes(index: i64) -> &'static [(Vec2, Vec2)] {
+    const TOP: (Vec2, Vec2) = (Vec2::new(0.0, 13.0), Vec2::new(32.0, 19.0));
+    const BOTTOM: (Vec2, Vec2) = (Vec2::new(0.0, -28.5), Vec2::new(32.0, 3.5));
+    const LEFT: (Vec2, Vec2) = (Vec2::new(-28.5, 0.0), Vec2::new(3.5, 32.0));
+    const RIGHT: (Vec2, Vec2) = (Vec2::new(28.5, 0.0), Vec2::new(3.5, 32.0));
+
     match index {
-        0 => vec![
-            (Vec2::new(0.0, 13.0), Collider::cuboid(32.0, 19.0)),
-            (Vec2::new(28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        1 | 3 => vec![
-            (Vec2::new(0.0, 13.0), Collider::cuboid(32.0, 19.0)),
-        ],
-        2  => vec![
-            (Vec2::new(0.0, 13.0), Collider::cuboid(32.0, 19.0)),
-            (Vec2::new(-28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        4 => vec![
-            (Vec2::new(-28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        8 => vec![
-            (Vec2::new(28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        7 => vec![
-            (Vec2::new(0.0, -28.5), Collider::cuboid(32.0, 3.5)),
-            (Vec2::new(28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        6 => vec![
-            (Vec2::new(0.0, -28.5), Collider::cuboid(32.0, 3.5)),
-            (Vec2::new(-28.5, 0.0), Collider::cuboid(3.5, 32.0)),
-        ],
-        
-        10 => vec![
-            (Vec2::new(0.0, -28.5), Collider::cuboid(32.0, 3.5)),
-        ],
-        _ => vec![],
+        0 => &[TOP, RIGHT],
+        1 | 3 => &[TOP],
+        2 => &[TOP, LEFT],
+        4 => &[LEFT],
+        8 => &[RIGHT],
+        7 => &[BOTTOM, RIGHT],
+        6 => &[BOTTOM, LEFT],
+        10 => &[BOTTOM],
+        _ => &[],
     }
 }
+
+pub fn get_collider(index: i64) -> Vec<(Vec2, Collider)> {
+    wall_boxes(index)
+        .iter()
+        .map(|&(offset, half_size)| (offset, Collider::cuboid(half_size.x, half_size.y)))
+        .collect()
+}
+
+/// Walls of the map in world coordinates, the same geometry as the colliders of the tiles.
+pub fn wall_rects() -> Vec<Rect> {
+    tiles()
+        .flat_map(|(row, col, index)| {
+            wall_boxes(index).iter().map(move |&(offset, half_size)| {
+                Rect::from_center_half_size(tile_position(row, col) + offset, half_size)
+            })
+        })
+        .collect()
+}